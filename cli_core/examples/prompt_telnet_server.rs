@@ -0,0 +1,60 @@
+extern crate terminal_cli;
+use terminal_cli::*;
+
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+struct SharedData {
+	val: u32,
+	prop: u32
+}
+
+fn main() {
+	let addr = env::args().nth(1).unwrap_or("127.0.0.1:3000".to_string());
+
+	let options = ConsoleServerOptions {
+		prompt: PromptBufferOptions { echo: true, ..Default::default() },
+		banner: "Rust terminal_cli telnet server demo.".into(),
+		idle_timeout: Some(Duration::from_secs(300)),
+		..Default::default()
+	};
+
+	let server = TelnetServer::bind(&addr, options).unwrap();
+	let handle = server.get_handle();
+
+	println!("Telnet server listening at {}", &addr);
+
+	let shared_data = Arc::new(Mutex::new(SharedData { val: 1, prop: 1 }));
+
+	server.run(move |m, session| {
+		if let Some(mut ctx) = m.property("shared_prop", validate_property_min_max(1, 100)) {
+			if let Ok(mut shared_data) = shared_data.lock() {
+				ctx.apply(&mut shared_data.prop);
+			}
+		}
+
+		if let Some(mut ctx) = m.command("inc") {
+			let val = shared_data.lock().map(|mut shared_data| {
+				shared_data.val += 1;
+				shared_data.val
+			});
+
+			if let Ok(val) = val {
+				ctx.get_terminal().print_line(&format!("New value: {}", val));
+			}
+		}
+
+		if let Some(mut ctx) = m.command("session") {
+			ctx.get_terminal().print_line(&format!("Session {} from {:?}", session.get_id(), session.get_peer_addr()));
+		}
+
+		if m.command("exit").is_some() {
+			session.close();
+		}
+
+		if m.command("shutdown").is_some() {
+			handle.shutdown();
+		}
+	}).unwrap();
+}
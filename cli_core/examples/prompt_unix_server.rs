@@ -0,0 +1,39 @@
+extern crate terminal_cli;
+use terminal_cli::*;
+
+use std::env;
+
+#[cfg(unix)]
+fn main() {
+	let path = env::args().nth(1).unwrap_or("/tmp/terminal_cli.sock".to_string());
+
+	let options = ConsoleServerOptions {
+		prompt: PromptBufferOptions { echo: true, ..Default::default() },
+		banner: "Rust terminal_cli admin console demo.".into(),
+		..Default::default()
+	};
+
+	let server = UnixConsoleServer::bind(&path, options).unwrap();
+	let handle = server.get_handle();
+
+	println!("Console listening at {}, attach with `terminal_cli_client {}`", &path, &path);
+
+	server.run(move |m, session| {
+		if let Some(mut ctx) = m.command("session") {
+			ctx.get_terminal().print_line(&format!("Session {}", session.get_id()));
+		}
+
+		if m.command("exit").is_some() {
+			session.close();
+		}
+
+		if m.command("shutdown").is_some() {
+			handle.shutdown();
+		}
+	}).unwrap();
+}
+
+#[cfg(not(unix))]
+fn main() {
+	println!("Unix domain sockets aren't supported on this platform.");
+}
@@ -1,5 +1,6 @@
 use prelude::v1::*;
 use utils::*;
+use tokenizer::*;
 
 /// A command that can be matched by the command line prompt
 #[derive(Debug, Clone, PartialEq)]
@@ -12,6 +13,39 @@ pub struct CliCommand<'a> {
 	pub help: Option<Cow<'a, str>>
 }
 
+type CompletionCallback<'a> = Box<Fn(&str) -> Vec<String> + 'a>;
+
+/// Source of the autocomplete suggestions for the values of the arguments
+pub enum CompletionProvider<'a> {
+	/// A fixed list of values
+	List(Vec<Cow<'a, str>>),
+	/// Called with the part of the value that was already typed, for suggestions that depend
+	/// on the live state, like the names of the connected devices
+	Callback(CompletionCallback<'a>)
+}
+
+impl<'a> CompletionProvider<'a> {
+	pub fn list<I, S>(values: I) -> CompletionProvider<'a>
+		where I: IntoIterator<Item = S>, S: Into<Cow<'a, str>>
+	{
+		CompletionProvider::List(values.into_iter().map(|v| v.into()).collect())
+	}
+
+	pub fn callback<F: Fn(&str) -> Vec<String> + 'a>(callback: F) -> CompletionProvider<'a> {
+		CompletionProvider::Callback(Box::new(callback))
+	}
+
+	/// The values that start with the typed part
+	pub fn complete(&self, partial: &str) -> Vec<String> {
+		let values = match *self {
+			CompletionProvider::List(ref values) => values.iter().map(|v| v.to_string()).collect(),
+			CompletionProvider::Callback(ref callback) => callback(partial)
+		};
+
+		values.into_iter().filter(|v| v.starts_with(partial)).collect()
+	}
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum CliError {
 	InvalidInput
@@ -36,7 +70,9 @@ pub struct AutocompleteLine {
 	/// The additional suggested part of the buffer, can be sent to the terminal device
 	additional_part_range: Range<usize>,
 	/// String that should be displayed on the autocomplete list
-	display_range: Range<usize>
+	display_range: Range<usize>,
+	/// Help of the suggested command, if it is complete
+	help: Option<String>
 }
 
 impl AutocompleteLine {
@@ -47,6 +83,42 @@ impl AutocompleteLine {
 	pub fn get_additional_part(&self) -> &str {
 		&self.full_new_line.index(self.additional_part_range.clone())
 	}
+
+	/// Help text of the suggested command. Suggestions that stop at a path separator don't have any.
+	pub fn get_help(&self) -> Option<&str> {
+		self.help.as_ref().map(|s| s.as_str())
+	}
+
+	/// Replace the `old` prefix of the suggested line with `new`. Used when the line
+	/// was matched relative to a path.
+	pub fn replace_prefix(&self, old: &str, new: &str) -> AutocompleteLine {
+		let rest = self.full_new_line.get(old.len()..).unwrap_or("");
+		let shift = |i: usize| i.saturating_sub(old.len()) + new.len();
+
+		AutocompleteLine {
+			full_new_line: format!("{}{}", new, rest),
+			additional_part_range: shift(self.additional_part_range.start)..shift(self.additional_part_range.end),
+			display_range: {
+				if self.display_range.start < old.len() {
+					0..shift(self.display_range.end)
+				} else {
+					shift(self.display_range.start)..shift(self.display_range.end)
+				}
+			},
+			help: self.help.clone()
+		}
+	}
+}
+
+/// The complete command in front of the arguments that are being typed
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnteredCommand {
+	pub command: String,
+	pub help: Option<String>,
+	/// Usage of the arguments, for the commands announced with an `ArgumentSchema`
+	pub usage: Option<String>,
+	/// The arguments typed so far include the required ones, the command can be executed
+	pub executable: bool
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -54,7 +126,9 @@ pub enum LineBufferResult {
 	MoreInputRequired { prefix_matches: Vec<String> },
 	NoMatchFound,
 	Match { args: String },
-	Autocomplete { result: AutocompleteResult }
+	Autocomplete { result: AutocompleteResult },
+	/// All the commands announced in the introspection mode, in the order of the announcement
+	Introspection { commands: Vec<CliCommand<'static>> }
 }
 
 /// Match commands against the given input line
@@ -64,7 +138,13 @@ pub struct CliLineMatcher<'a> {
 	line_prefix: Option<String>,
 	mode: LineMatcherMode,	
 	path_separator: Option<char>,
-    state: LineBufferResult
+    state: LineBufferResult,
+	/// Help of the complete commands among the autocomplete suggestions
+	help: Vec<(String, String)>,
+	/// Where the display of the argument value suggestions starts
+	display_from: Vec<(String, usize)>,
+	/// The command whose arguments are being typed, in the autocomplete mode
+	entered: Option<EnteredCommand>
 }
 
 /// State of the line matcher
@@ -80,7 +160,10 @@ pub enum LineMatcherProgress {
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum LineMatcherMode {
 	Execute,
-	AutocompleteOnly
+	AutocompleteOnly,
+	/// Don't match anything, collect the commands starting with the line along with their
+	/// help. Used by the built-in `help` command.
+	Introspect
 }
 
 impl<'a> CliLineMatcher<'a> {
@@ -91,8 +174,17 @@ impl<'a> CliLineMatcher<'a> {
 			line_trimmed: line.trim(),
 			mode: mode,
 			line_prefix: None,
-			state: LineBufferResult::MoreInputRequired { prefix_matches: Vec::new() },
-			path_separator: Some('/')
+			state: {
+				if mode == LineMatcherMode::Introspect {
+					LineBufferResult::Introspection { commands: Vec::new() }
+				} else {
+					LineBufferResult::MoreInputRequired { prefix_matches: Vec::new() }
+				}
+			},
+			path_separator: Some('/'),
+			help: Vec::new(),
+			display_from: Vec::new(),
+			entered: None
 		}
 	}
 
@@ -108,7 +200,13 @@ impl<'a> CliLineMatcher<'a> {
         &self.state
     }
 
+	/// Does the line start with the prefix? In the introspection mode, also accepts the
+	/// prefixes that start with the line, to collect all the commands under them.
 	pub fn starts_with(&self, cmd: &str) -> bool {
+		if self.mode == LineMatcherMode::Introspect && cmd.starts_with(self.line_trimmed) {
+			return true;
+		}
+
 		self.line_trimmed.starts_with(cmd)
 	}
 
@@ -120,6 +218,11 @@ impl<'a> CliLineMatcher<'a> {
 
 	/// Match the command, mutates the internal state of the matching
 	pub fn match_cmd<'b>(&mut self, cmd: &'b CliCommand<'b>) -> LineMatcherProgress {
+		if self.mode == LineMatcherMode::Introspect {
+			self.introspect(cmd);
+			return LineMatcherProgress::Processing;
+		}
+
 		let prefixed = if let Some(ref prefix) = self.line_prefix {
 			let cmd = format!("{}{}", prefix, &cmd.command);
 			Some((cmd, prefix.to_string()))
@@ -127,19 +230,25 @@ impl<'a> CliLineMatcher<'a> {
 			None
 		};
 
+		let help = cmd.help.as_ref().map(|h| h.as_ref());
+
 		if let Some((cmd, prefix)) = prefixed {
-			let r = self.match_cmd_str(&cmd, Some(&prefix));
+			let r = self.match_cmd_help(&cmd, Some(&prefix), help);
 
 			if r != LineMatcherProgress::Processing {
 				return r;
 			}
 		}
 
-		self.match_cmd_str(&cmd.command, None)
+		self.match_cmd_help(&cmd.command, None, help)
 	}
 
     /// Match the string, mutates the internal state
 	pub fn match_cmd_str<'b>(&mut self, cmd: &'b str, prefix: Option<&'b str>) -> LineMatcherProgress {
+		self.match_cmd_help(cmd, prefix, None)
+	}
+
+	fn match_cmd_help(&mut self, cmd: &str, prefix: Option<&str>, help: Option<&str>) -> LineMatcherProgress {
 		match self.state {
             LineBufferResult::MoreInputRequired { .. } => (),
             _ => { return LineMatcherProgress::Skipped; }
@@ -148,19 +257,31 @@ impl<'a> CliLineMatcher<'a> {
 		let c = cmd.len();
 		if c == 0 { return LineMatcherProgress::Processing; }		
 
+		if self.get_partial_args(cmd).is_some() {
+			// the longest command wins, its arguments are the shortest
+			let cmd = cmd.trim_end();
+			if self.entered.as_ref().map_or(true, |e| e.command.len() < cmd.len()) {
+				self.entered = Some(EnteredCommand {
+					command: cmd.to_string(),
+					help: help.map(|h| h.to_string()),
+					usage: None,
+					executable: true
+				});
+			}
+		}
+
 		let cmd_ends_with_sep = {
 			let l = cmd.chars().last();
 			l == Some(' ')
 		};
-		let at_sep = self.line.chars().skip(cmd.len()).next();
+		let at_sep = self.line.get(c..).and_then(|rest| rest.chars().next());
 
 		if self.mode == LineMatcherMode::Execute &&
 		   self.line.len() >= c &&
 		   self.line.starts_with(&*cmd) &&
 		   (cmd_ends_with_sep || at_sep == None || at_sep == Some(' '))
 		{			
-			let args: String = self.line.chars().skip(cmd.len()).collect();
-			let args = args.trim_left().to_string();
+			let args = self.line[c..].trim_left().to_string();
 			self.state = LineBufferResult::Match { args: args };
 			return LineMatcherProgress::MatchFound;
 		} else if self.mode == LineMatcherMode::AutocompleteOnly && cmd.starts_with(self.line) {
@@ -186,6 +307,7 @@ impl<'a> CliLineMatcher<'a> {
 				match c.find(sep) {
 					None => {
 						self.push_prefix_match(cmd.to_string());
+						self.push_help(cmd, help);
 					},
 					Some(l) => {
 						self.push_prefix_match(format!("{}{}{}", prefix, &c[..l], sep));
@@ -194,12 +316,109 @@ impl<'a> CliLineMatcher<'a> {
 
 			} else {
 				self.push_prefix_match(cmd.to_string());
+				self.push_help(cmd, help);
 			}			
 		}
 
 		LineMatcherProgress::Processing
 	}
 
+	/// Record the command in the introspection mode
+	fn introspect(&mut self, cmd: &CliCommand) {
+		let command = match self.line_prefix {
+			Some(ref prefix) => format!("{}{}", prefix, &cmd.command),
+			None => cmd.command.to_string()
+		};
+
+		if !command.starts_with(self.line_trimmed) {
+			return;
+		}
+
+		if let LineBufferResult::Introspection { ref mut commands } = self.state {
+			commands.push(CliCommand {
+				command: command.into(),
+				help: cmd.help.as_ref().map(|h| h.to_string().into())
+			});
+		}
+	}
+
+	/// The arguments typed so far after the command, if the line is being autocompleted and
+	/// the command is complete
+	pub fn get_partial_args(&self, cmd: &str) -> Option<&'a str> {
+		if self.mode != LineMatcherMode::AutocompleteOnly {
+			return None;
+		}
+
+		if let LineBufferResult::MoreInputRequired { .. } = self.state { } else {
+			return None;
+		}
+
+		let cmd = cmd.trim_end();
+		if !self.line.starts_with(cmd) || !self.line[cmd.len()..].starts_with(' ') {
+			return None;
+		}
+
+		Some(&self.line[cmd.len() + 1..])
+	}
+
+	/// Record the usage of the command's arguments, if they are being typed, and whether
+	/// the arguments typed so far include the required ones
+	pub fn set_entered_usage(&mut self, cmd: &str, usage: &str, executable: bool) {
+		if let Some(ref mut entered) = self.entered {
+			if entered.command == cmd.trim_end() {
+				entered.usage = Some(usage.to_string());
+				entered.executable = executable;
+			}
+		}
+	}
+
+	/// The complete command in front of the arguments that are being typed, in the
+	/// autocomplete mode
+	pub fn get_entered_command(&self) -> Option<&EnteredCommand> {
+		self.entered.as_ref()
+	}
+
+	/// Suggest the values of the word being typed after the command, in the autocomplete
+	/// mode. The suggestions replace the last word of the line and are quoted when needed,
+	/// also when the word is inside an open quote.
+	pub fn match_argument_values<S: AsRef<str>>(&mut self, cmd: &str, values: &[(S, Option<&str>)]) {
+		if self.get_partial_args(cmd).is_none() {
+			return;
+		}
+
+		let word = partial_argument(self.line);
+
+		for &(ref value, help) in values {
+			let value = value.as_ref();
+			if value.starts_with(&word.value) {
+				let candidate = format!("{}{}", &self.line[..word.start], quote_argument(value, word.open_quote));
+				self.display_from.push((candidate.clone(), word.start));
+				self.push_help(&candidate, help);
+				self.push_prefix_match(candidate);
+			}
+		}
+	}
+
+	/// Length of the part of the line that the suggestion keeps. Shorter than the line when
+	/// the suggestion quotes the word being typed.
+	fn unchanged_length(&self, new_line: &str) -> usize {
+		self.line.char_indices()
+			.zip(new_line.chars())
+			.find(|&((_, a), b)| a != b)
+			.map(|((i, _), _)| i)
+			.unwrap_or_else(|| min(self.line.len(), new_line.len()))
+	}
+
+	fn push_help(&mut self, cmd: &str, help: Option<&str>) {
+		if let Some(help) = help {
+			self.help.push((cmd.to_string(), help.to_string()));
+		}
+	}
+
+	fn find_help(&self, cmd: &str) -> Option<String> {
+		self.help.iter().find(|h| h.0 == cmd).map(|h| h.1.clone())
+	}
+
 	/// Maintain a list of unique prefixes!
 	fn push_prefix_match(&mut self, s: String) {
 		if let LineBufferResult::MoreInputRequired { ref mut prefix_matches } = self.state {
@@ -223,11 +442,12 @@ impl<'a> CliLineMatcher<'a> {
 			LineBufferResult::MoreInputRequired { .. } => (),
             LineBufferResult::NoMatchFound => { return LineBufferResult::NoMatchFound; },
             p @ LineBufferResult::Match { .. } => { return p; },
-            p @ LineBufferResult::Autocomplete { .. } => { return p; }
+            p @ LineBufferResult::Autocomplete { .. } => { return p; },
+            p @ LineBufferResult::Introspection { .. } => { return p; }
         };
 
-		match (self.mode, self.state) {
-			(LineMatcherMode::AutocompleteOnly, LineBufferResult::MoreInputRequired { prefix_matches }) => {
+		match self.state {
+			LineBufferResult::MoreInputRequired { ref prefix_matches } if self.mode == LineMatcherMode::AutocompleteOnly => {
 
 				let autocomplete = match prefix_matches.len() {
 					0 => AutocompleteResult::None,
@@ -236,51 +456,15 @@ impl<'a> CliLineMatcher<'a> {
 						let full_new_line_length = full_new_line.len();
 
 						let l = AutocompleteLine { 
+							help: self.find_help(&full_new_line),
+							additional_part_range: self.unchanged_length(&full_new_line)..full_new_line_length,
 							full_new_line: full_new_line,
-							additional_part_range: self.line.len()..full_new_line_length,
 							display_range: 0..full_new_line_length
 						};
 						AutocompleteResult::SingleMatch { line: l }
 					}
 					_ => {			
-						let mut lines = Vec::new();
-						for prefix_match in prefix_matches.into_iter() {
-							
-							let full_new_line = prefix_match;
-							let full_new_line_length = full_new_line.len();
-
-							let display_range = {
-								if let Some(sep) = self.path_separator {
-									let l = self.line.rfind(sep);
-									let r = full_new_line.rfind(sep);
-									match (l, r) {
-										(Some(l), Some(r)) => {
-											let p = min(l, r) + 1;
-											if full_new_line.len() > p {
-												p..full_new_line_length
-											} else {
-												0..full_new_line_length
-											}
-										},
-										_ => {
-											0..full_new_line_length
-										}
-									}
-								} else {
-									0..full_new_line_length
-								}
-							};
-
-							let l = AutocompleteLine {
-								full_new_line: full_new_line,
-								additional_part_range: self.line.len()..full_new_line_length,
-								display_range: display_range
-							};
-							lines.push(l);
-						}
-
-						// sort the lines
-						lines.sort_by(|a, b| { a.full_new_line.cmp(&b.full_new_line) });
+						let lines = self.candidate_lines(prefix_matches);
 
 						// todo: separator and non-separator modes!
 						// lcp is already properly computed!
@@ -290,7 +474,8 @@ impl<'a> CliLineMatcher<'a> {
 
 							let lcp = longest_common_prefix(&line_strings);
 							if let Some(lcp) = lcp {							
-								if lcp.len() == self.line.len() {
+								// quoted suggestions don't necessarily extend the line
+								if lcp.len() <= self.line.len() || !lcp.starts_with(self.line) {
 									None
 								} else {
 									Some(lcp.to_string())
@@ -303,9 +488,10 @@ impl<'a> CliLineMatcher<'a> {
 						if let Some(lcp) = lcp {
 							AutocompleteResult::SingleMatch { 
 								line: AutocompleteLine {
-									additional_part_range: self.line.len()..lcp.len(),
+									additional_part_range: self.unchanged_length(&lcp)..lcp.len(),
 									display_range: 0..lcp.len(),
-									full_new_line: lcp								
+									full_new_line: lcp,
+									help: None
 								}
 							}
 						} else {
@@ -316,7 +502,67 @@ impl<'a> CliLineMatcher<'a> {
 
 				LineBufferResult::Autocomplete { result: autocomplete }
 			},
-			(_, _) => LineBufferResult::NoMatchFound
+			_ => LineBufferResult::NoMatchFound
+		}
+	}
+
+	/// Finish the autocomplete matching with all the suggestions, without merging them into
+	/// their common prefix. Used for listing the possible continuations of the line.
+	pub fn finish_candidates(self) -> Vec<AutocompleteLine> {
+		match self.state {
+			LineBufferResult::MoreInputRequired { ref prefix_matches } if self.mode == LineMatcherMode::AutocompleteOnly => {
+				self.candidate_lines(prefix_matches)
+			},
+			_ => vec![]
 		}
 	}
+
+	/// Sorted suggestions, displayed from the last path separator on
+	fn candidate_lines(&self, prefix_matches: &[String]) -> Vec<AutocompleteLine> {
+		let mut lines = Vec::new();
+		for prefix_match in prefix_matches {
+			
+			let full_new_line = prefix_match.to_string();
+			let full_new_line_length = full_new_line.len();
+
+			let display_from = self.display_from.iter().find(|d| d.0 == full_new_line).map(|d| d.1);
+
+			let display_range = {
+				if let Some(from) = display_from {
+					from..full_new_line_length
+				} else if let Some(sep) = self.path_separator {
+					let l = self.line.rfind(sep);
+					let r = full_new_line.rfind(sep);
+					match (l, r) {
+						(Some(l), Some(r)) => {
+							let p = min(l, r) + 1;
+							if full_new_line.len() > p {
+								p..full_new_line_length
+							} else {
+								0..full_new_line_length
+							}
+						},
+						_ => {
+							0..full_new_line_length
+						}
+					}
+				} else {
+					0..full_new_line_length
+				}
+			};
+
+			let l = AutocompleteLine {
+				help: self.find_help(&full_new_line),
+				additional_part_range: self.unchanged_length(&full_new_line)..full_new_line_length,
+				full_new_line: full_new_line,
+				display_range: display_range
+			};
+			lines.push(l);
+		}
+
+		// sort the lines
+		lines.sort_by(|a, b| { a.full_new_line.cmp(&b.full_new_line) });
+
+		lines
+	}
 }
@@ -4,7 +4,9 @@ use terminal::*;
 use autocomplete::*;
 use cli_property::*;
 use cli_command::*;
-use super::i18n::Strings;
+use cli_args::*;
+use tokenizer::*;
+use super::i18n::{Strings, English};
 
 pub trait CliContext<'a> {
 	/// Creates a new prefixed execution context, but only if the current line matches. Reduces the
@@ -14,16 +16,85 @@ pub trait CliContext<'a> {
 	/// Announces a command to be executed. Returns an execution context in case the command is invoked.
 	fn command<'b>(&'b mut self, cmd: &str) -> Option<CommandContext<'b>>;
 
+	/// Announces a command along with its help text, shown by the built-in `help` command.
+	/// The first line of the text is its short description. The default implementation
+	/// ignores the help.
+	fn command_with_help<'b>(&'b mut self, cmd: &str, _help: &str) -> Option<CommandContext<'b>> {
+		self.command(cmd)
+	}
+
+	/// Announces a command with declared arguments. The arguments are parsed before the command
+	/// is executed, on a usage error the error is printed along with the usage and `None` is
+	/// returned. The schema is also shown by the built-in `help` and completes the option names.
+	/// The default implementation only parses the arguments, the errors are printed in English.
+	fn command_with_args<'b>(&'b mut self, cmd: &str, help: &str, schema: &ArgumentSchema) -> Option<CommandContext<'b>> {
+		let ctx = self.command_with_help(cmd, help)?;
+		parse_arguments(ctx, cmd, schema, &English)
+	}
+
+	/// Attach a completion provider to the arguments of a command. The suggestions replace the
+	/// word being typed after the command. The command itself is announced separately.
+	/// The default implementation doesn't suggest anything.
+	fn complete_args(&mut self, _cmd: &str, _completion: &CompletionProvider) { }
+
+	/// Announces a command whose arguments are completed by the provider.
+	fn command_with_completion<'b>(&'b mut self, cmd: &str, help: &str, completion: &CompletionProvider) -> Option<CommandContext<'b>> {
+		self.complete_args(cmd, completion);
+		self.command_with_help(cmd, help)
+	}
+
 	/// Announces a property that can be manipulated. Returns an execution context in case the property
 	/// is to be either retrieved or updated.
 	fn property<'b, V, P, Id: Into<Cow<'b, str>>>(&'b mut self, property_id: Id, input_parser: P) -> Option<PropertyContext<'b, V>> where P: ValueInput<V>, V: Display;
+
+	/// Announces a property along with its help text, shown by the built-in `help` command.
+	/// The default implementation ignores the help.
+	fn property_with_help<'b, V, P, Id: Into<Cow<'b, str>>>(&'b mut self, property_id: Id, input_parser: P, _help: &str) -> Option<PropertyContext<'b, V>> where P: ValueInput<V>, V: Display {
+		self.property(property_id, input_parser)
+	}
+
+	/// Announces a property whose new values are completed by the provider.
+	fn property_with_completion<'b, V, P, Id: Into<Cow<'b, str>>>(&'b mut self, property_id: Id, input_parser: P, help: &str, completion: &CompletionProvider) -> Option<PropertyContext<'b, V>> where P: ValueInput<V>, V: Display {
+		let property_id: Cow<str> = property_id.into();
+		self.complete_args(&format!("{}/set", property_id), completion);
+		self.property_with_help(property_id, input_parser, help)
+	}
+}
+
+/// Parse the arguments of the invoked command, report a usage error along with the usage
+fn parse_arguments<'b>(mut ctx: CommandContext<'b>, cmd: &str, schema: &ArgumentSchema, strings: &Strings) -> Option<CommandContext<'b>> {
+	match schema.parse(&ctx.args) {
+		Ok(arguments) => {
+			ctx.arguments = arguments;
+			Some(ctx)
+		},
+		Err(e) => {
+			strings.argument_error(ctx.terminal, &e).ok();
+			ctx.terminal.newline();
+			strings.command_usage(ctx.terminal, cmd, &schema.usage()).ok();
+			ctx.terminal.newline();
+			ctx.fail();
+			None
+		}
+	}
+}
+
+/// The announced command, with the help text if there is any
+fn cli_command<'c>(cmd: &'c str, help: &'c str) -> CliCommand<'c> {
+	CliCommand {
+		command: cmd.into(),
+		help: if help.is_empty() { None } else { Some(help.into()) }
+	}
 }
 
 /// Helper for matching commands and properties against an input line.
 pub struct CliExecutor<'a> {
 	matcher: CliLineMatcher<'a>,
 	strings: &'a Strings,
-	terminal: &'a mut CharacterTerminalWriter
+	terminal: &'a mut CharacterTerminalWriter,
+	current_path: &'a str,
+	question: Option<AskedQuestion>,
+	failed: bool
 }
 
 impl<'a> CliContext<'a> for CliExecutor<'a> {	
@@ -41,10 +112,14 @@ impl<'a> CliContext<'a> for CliExecutor<'a> {
 		
 		None
 	}
-	
+
 	fn command<'b>(&'b mut self, cmd: &str) -> Option<CommandContext<'b>> {
+		self.command_with_help(cmd, "")
+	}
+	
+	fn command_with_help<'b>(&'b mut self, cmd: &str, help: &str) -> Option<CommandContext<'b>> {
 
-		if self.matcher.match_cmd_str(cmd, None) == LineMatcherProgress::MatchFound {
+		if self.matcher.match_cmd(&cli_command(cmd, help)) == LineMatcherProgress::MatchFound {
 			let args = if let &LineBufferResult::Match { ref args, .. } = self.matcher.get_state() {
 				Some(args.clone())
 			} else {
@@ -52,23 +127,59 @@ impl<'a> CliContext<'a> for CliExecutor<'a> {
 			};
 
 			if let Some(args) = args {
-				let ctx = CommandContext {
-					args: args.into(),
-					terminal: self.terminal,
-					current_path: ""
-				};
-				
+				let ctx = CommandContext::new(args.into(), self.terminal, self.current_path, &mut self.question, &mut self.failed);
 				return Some(ctx);
 			}
 		}
 
 		None
 	}
-		
+
+	fn command_with_args<'b>(&'b mut self, cmd: &str, help: &str, schema: &ArgumentSchema) -> Option<CommandContext<'b>> {
+		let strings = self.strings;
+
+		match self.matcher.get_mode() {
+			LineMatcherMode::Introspect => {
+				let usage = format!("{} {}", cmd, schema.usage());
+				let args_help = schema.help();
+				let help: Vec<&str> = [help, &usage, &args_help].iter().cloned().filter(|s| !s.is_empty()).collect();
+				return self.command_with_help(cmd, &help.join("\n"));
+			},
+			LineMatcherMode::AutocompleteOnly => {
+				let executable = match self.matcher.get_partial_args(cmd) {
+					Some(args) => {
+						self.matcher.match_argument_values(cmd, &schema.complete(args));
+						!matches!(schema.parse(args), Err(ArgumentError::Missing { .. }))
+					},
+					None => true
+				};
+
+				self.command_with_help(cmd, help);
+				self.matcher.set_entered_usage(cmd, &schema.usage(), executable);
+				return None;
+			},
+			LineMatcherMode::Execute => ()
+		}
+
+		let ctx = self.command_with_help(cmd, help)?;
+		parse_arguments(ctx, cmd, schema, strings)
+	}
+
+	fn complete_args(&mut self, cmd: &str, completion: &CompletionProvider) {
+		if let Some(args) = self.matcher.get_partial_args(cmd) {
+			let values: Vec<(String, Option<&str>)> = completion.complete(&partial_argument(args).value).into_iter().map(|v| (v, None)).collect();
+			self.matcher.match_argument_values(cmd, &values);
+		}
+	}
+
 	fn property<'b, V, P, Id: Into<Cow<'b, str>>>(&'b mut self, property_id: Id, input_parser: P) -> Option<PropertyContext<'b, V>> where P: ValueInput<V>, V: Display {
+		self.property_with_help(property_id, input_parser, "")
+	}
+		
+	fn property_with_help<'b, V, P, Id: Into<Cow<'b, str>>>(&'b mut self, property_id: Id, input_parser: P, help: &str) -> Option<PropertyContext<'b, V>> where P: ValueInput<V>, V: Display {
 		let property_id: Cow<str> = property_id.into();
 
-		if self.matcher.match_cmd_str(&format!("{}/get", property_id), None) == LineMatcherProgress::MatchFound {
+		if self.matcher.match_cmd(&cli_command(&format!("{}/get", property_id), help)) == LineMatcherProgress::MatchFound {
 			let args = if let &LineBufferResult::Match { ref args, .. } = self.matcher.get_state() {
 				args.clone()
 			} else {
@@ -79,7 +190,7 @@ impl<'a> CliContext<'a> for CliExecutor<'a> {
 				common: PropertyContextCommon {
 					args: args.into(),
 					terminal: self.terminal,
-					current_path: "",
+					current_path: self.current_path,
 					id: property_id,
 					style: PropertyCommandStyle::DelimitedGetSet,
 					strings: &*self.strings
@@ -87,7 +198,7 @@ impl<'a> CliContext<'a> for CliExecutor<'a> {
 			}));
 		}
 
-		if self.matcher.match_cmd_str(&format!("{}/set", property_id), None) == LineMatcherProgress::MatchFound {
+		if self.matcher.match_cmd(&cli_command(&format!("{}/set", property_id), help)) == LineMatcherProgress::MatchFound {
 			let args = if let &LineBufferResult::Match { ref args, .. } = self.matcher.get_state() {
 				args.trim()
 			} else {
@@ -100,7 +211,7 @@ impl<'a> CliContext<'a> for CliExecutor<'a> {
 						common: PropertyContextCommon {
 							args: args.into(),
 							terminal: self.terminal,
-							current_path: "",
+							current_path: self.current_path,
 							id: property_id,
 							style: PropertyCommandStyle::DelimitedGetSet,
 							strings: &*self.strings
@@ -123,6 +234,7 @@ impl<'a> CliContext<'a> for CliExecutor<'a> {
 					}
 
 					self.terminal.newline();
+					self.failed = true;
 				}
 			}
 		}
@@ -136,10 +248,28 @@ impl<'a> CliExecutor<'a> {
 		CliExecutor {
 			matcher: matcher,
 			strings: strings,
-			terminal: terminal
+			terminal: terminal,
+			current_path: "",
+			question: None,
+			failed: false
 		}
 	}
 
+	/// The path the user navigated to, passed on to the commands and properties.
+	pub fn set_current_path(&mut self, current_path: &'a str) {
+		self.current_path = current_path;
+	}
+
+	/// Question asked by the executed command, if any, along with its answer handler.
+	pub fn take_question(&mut self) -> Option<AskedQuestion> {
+		self.question.take()
+	}
+
+	/// Did the executed command report a failure, or was a property given an invalid value?
+	pub fn is_failed(&self) -> bool {
+		self.failed
+	}
+
 	/// Finish the execution of this line invocation.
 	pub fn close(self) -> CliLineMatcher<'a> {
 		self.matcher
@@ -181,15 +311,35 @@ impl<'a, 'p> CliContext<'a> for PrefixedExecutor<'a, 'p> {
 	}
 
 	fn command<'b>(&'b mut self, cmd: &str) -> Option<CommandContext<'b>> {
+		self.command_with_help(cmd, "")
+	}
+
+	fn command_with_help<'b>(&'b mut self, cmd: &str, help: &str) -> Option<CommandContext<'b>> {
 		let cmd = self.add_prefix(cmd);
 
-		self.executor.command(&cmd)
+		self.executor.command_with_help(&cmd, help)
 	}
-	
+
+	fn command_with_args<'b>(&'b mut self, cmd: &str, help: &str, schema: &ArgumentSchema) -> Option<CommandContext<'b>> {
+		let cmd = self.add_prefix(cmd);
+
+		self.executor.command_with_args(&cmd, help, schema)
+	}
+
+	fn complete_args(&mut self, cmd: &str, completion: &CompletionProvider) {
+		let cmd = self.add_prefix(cmd);
+
+		self.executor.complete_args(&cmd, completion)
+	}
+
 	fn property<'b, V, P, Id: Into<Cow<'b, str>>>(&'b mut self, property_id: Id, input_parser: P) -> Option<PropertyContext<'b, V>> where P: ValueInput<V>, V: Display {
+		self.property_with_help(property_id, input_parser, "")
+	}
+	
+	fn property_with_help<'b, V, P, Id: Into<Cow<'b, str>>>(&'b mut self, property_id: Id, input_parser: P, help: &str) -> Option<PropertyContext<'b, V>> where P: ValueInput<V>, V: Display {
 		let property_id: Cow<str> = property_id.into();
 		let property_id = self.add_prefix(&property_id);
 
-		self.executor.property(property_id, input_parser)
+		self.executor.property_with_help(property_id, input_parser, help)
 	}
 }
\ No newline at end of file
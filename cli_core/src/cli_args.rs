@@ -0,0 +1,408 @@
+use prelude::v1::*;
+use property::*;
+use tokenizer::*;
+use autocomplete::*;
+
+/// How an argument is given on the command line
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ArgumentKind {
+	/// A value identified by its position among the arguments that aren't options
+	Positional,
+	/// `--name`, without a value
+	Flag,
+	/// `--name value` or `--name=value`
+	Option
+}
+
+/// Why the arguments of a command couldn't be parsed. The arguments are named as in the usage.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgumentError {
+	/// A required argument wasn't given
+	Missing { name: String },
+	/// More positional arguments than the command accepts
+	Unexpected { arg: String },
+	/// An option or a flag the command doesn't declare
+	UnknownOption { name: String },
+	/// An option without its value
+	MissingValue { name: String },
+	/// The value was rejected by the argument's `ValueInput`
+	InvalidValue { name: String, value: String },
+	ValueTooSmall { name: String, val: String, min: String },
+	ValueTooBig { name: String, val: String, max: String }
+}
+
+type ValueParser<'a> = Box<Fn(&str) -> Result<Box<Any>, PropertyValidationError<String>> + 'a>;
+
+/// Declaration of a single command argument, part of an `ArgumentSchema`.
+pub struct Argument<'a> {
+	name: Cow<'a, str>,
+	kind: ArgumentKind,
+	required: bool,
+	help: Option<Cow<'a, str>>,
+	parser: Option<ValueParser<'a>>,
+	completion: Option<CompletionProvider<'a>>
+}
+
+impl<'a> Argument<'a> {
+	fn new<N: Into<Cow<'a, str>>>(name: N, kind: ArgumentKind, required: bool) -> Argument<'a> {
+		Argument {
+			name: name.into(),
+			kind: kind,
+			required: required,
+			help: None,
+			parser: None,
+			completion: None
+		}
+	}
+
+	/// A required positional argument
+	pub fn positional<N: Into<Cow<'a, str>>>(name: N) -> Argument<'a> {
+		Argument::new(name, ArgumentKind::Positional, true)
+	}
+
+	/// An optional `--name` flag
+	pub fn flag<N: Into<Cow<'a, str>>>(name: N) -> Argument<'a> {
+		Argument::new(name, ArgumentKind::Flag, false)
+	}
+
+	/// An optional `--name value` option
+	pub fn option<N: Into<Cow<'a, str>>>(name: N) -> Argument<'a> {
+		Argument::new(name, ArgumentKind::Option, false)
+	}
+
+	pub fn optional(mut self) -> Argument<'a> {
+		self.required = false;
+		self
+	}
+
+	pub fn required(mut self) -> Argument<'a> {
+		self.required = true;
+		self
+	}
+
+	/// Help for the argument, shown by the built-in `help` command
+	pub fn help<H: Into<Cow<'a, str>>>(mut self, help: H) -> Argument<'a> {
+		self.help = Some(help.into());
+		self
+	}
+
+	/// Suggest the values of the argument when autocompleting
+	pub fn complete(mut self, completion: CompletionProvider<'a>) -> Argument<'a> {
+		self.completion = Some(completion);
+		self
+	}
+
+	/// Parse the value with the input parser, for example `validate_property_min_max`. The
+	/// parsed value is retrieved with `ParsedArguments::get`. Without a parser, the values
+	/// are `String`s.
+	pub fn value<T, P>(mut self, parser: P) -> Argument<'a>
+		where T: Display + 'static, P: ValueInput<T> + 'a
+	{
+		self.parser = Some(Box::new(move |s| {
+			match parser.input(s) {
+				Ok(v) => Ok(Box::new(v) as Box<Any>),
+				Err(PropertyValidationError::InvalidInput) => Err(PropertyValidationError::InvalidInput),
+				Err(PropertyValidationError::ValueTooSmall { min, val }) => {
+					Err(PropertyValidationError::ValueTooSmall { min: min.to_string(), val: val.to_string() })
+				},
+				Err(PropertyValidationError::ValueTooBig { max, val }) => {
+					Err(PropertyValidationError::ValueTooBig { max: max.to_string(), val: val.to_string() })
+				}
+			}
+		}));
+		self
+	}
+
+	pub fn get_name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn get_kind(&self) -> ArgumentKind {
+		self.kind
+	}
+
+	pub fn is_required(&self) -> bool {
+		self.required
+	}
+
+	pub fn get_help(&self) -> Option<&str> {
+		self.help.as_ref().map(|h| h.as_ref())
+	}
+
+	/// Name of the argument in the error messages, `<name>` or `--name`
+	pub fn get_display_name(&self) -> String {
+		match self.kind {
+			ArgumentKind::Positional => format!("<{}>", self.name),
+			ArgumentKind::Flag | ArgumentKind::Option => format!("--{}", self.name)
+		}
+	}
+
+	/// The argument as shown in the usage, `<name>`, `--name` or `--name <name>`. Optional
+	/// arguments are in brackets.
+	pub fn get_usage(&self) -> String {
+		let usage = match self.kind {
+			ArgumentKind::Option => format!("--{} <{}>", self.name, self.name),
+			_ => self.get_display_name()
+		};
+
+		if self.required {
+			usage
+		} else {
+			format!("[{}]", usage)
+		}
+	}
+
+	fn parse_value(&self, value: &str) -> Result<ParsedValue, ArgumentError> {
+		let parsed = match self.parser {
+			Some(ref parser) => {
+				let name = self.get_display_name();
+				match parser(value) {
+					Ok(v) => Some(v),
+					Err(PropertyValidationError::InvalidInput) => {
+						return Err(ArgumentError::InvalidValue { name: name, value: value.into() });
+					},
+					Err(PropertyValidationError::ValueTooSmall { min, val }) => {
+						return Err(ArgumentError::ValueTooSmall { name: name, val: val, min: min });
+					},
+					Err(PropertyValidationError::ValueTooBig { max, val }) => {
+						return Err(ArgumentError::ValueTooBig { name: name, val: val, max: max });
+					}
+				}
+			},
+			None => None
+		};
+
+		Ok(ParsedValue {
+			name: self.name.to_string(),
+			raw: value.into(),
+			value: parsed
+		})
+	}
+}
+
+/// The arguments a command accepts, for `CliContext::command_with_args`. Parses the
+/// arguments of the command line and describes them in the help and the autocomplete.
+///
+/// ```
+/// # use terminal_cli::*;
+/// let schema = ArgumentSchema::new()
+///     .arg(Argument::positional("ssid").help("Name of the network"))
+///     .arg(Argument::flag("hidden"))
+///     .arg(Argument::option("channel").value(validate_property_min_max(1u8, 13)));
+///
+/// assert_eq!("<ssid> [--hidden] [--channel <channel>]", schema.usage());
+///
+/// let args = schema.parse("home --channel 6").unwrap();
+/// assert_eq!(Some("home".to_string()), args.get("ssid"));
+/// assert_eq!(Some(6u8), args.get("channel"));
+/// assert!(!args.has("hidden"));
+/// ```
+#[derive(Default)]
+pub struct ArgumentSchema<'a> {
+	arguments: Vec<Argument<'a>>
+}
+
+impl<'a> ArgumentSchema<'a> {
+	pub fn new() -> ArgumentSchema<'a> {
+		ArgumentSchema { arguments: Vec::new() }
+	}
+
+	/// Add an argument. The positional arguments are matched in the order they are added.
+	pub fn arg(mut self, argument: Argument<'a>) -> ArgumentSchema<'a> {
+		self.arguments.push(argument);
+		self
+	}
+
+	pub fn get_arguments(&self) -> &[Argument<'a>] {
+		&self.arguments
+	}
+
+	/// The arguments as shown after the command in its usage
+	pub fn usage(&self) -> String {
+		let usage: Vec<String> = self.arguments.iter().map(|a| a.get_usage()).collect();
+		usage.join(" ")
+	}
+
+	/// The help of the arguments, one line per argument
+	pub fn help(&self) -> String {
+		let width = self.arguments.iter().map(|a| a.get_display_name().len()).max().unwrap_or(0);
+
+		let lines: Vec<String> = self.arguments.iter().map(|a| {
+			let name = a.get_display_name();
+			match a.get_help() {
+				Some(help) => format!("{}{}{}", name, " ".repeat(width - name.len() + 2), help),
+				None => name
+			}
+		}).collect();
+
+		lines.join("\n")
+	}
+
+	/// The flags and the options with their help, as they are typed
+	pub fn get_option_names(&self) -> Vec<(String, Option<&str>)> {
+		self.arguments.iter()
+			.filter(|a| a.kind != ArgumentKind::Positional)
+			.map(|a| (a.get_display_name(), a.get_help()))
+			.collect()
+	}
+
+	/// Autocomplete suggestions for the last word of the arguments: the values of the argument
+	/// at its position, and the names of the options unless the word is an option's value.
+	pub fn complete(&self, args: &str) -> Vec<(String, Option<&str>)> {
+		let word = partial_argument(args);
+		let mut positional = self.arguments.iter().filter(|a| a.kind == ArgumentKind::Positional);
+		let mut value_of = None;
+		let mut options_ended = false;
+
+		for w in tokenize_arguments(&args[..word.start]) {
+			if value_of.take().is_some() {
+				continue;
+			}
+
+			if !options_ended && w == "--" {
+				options_ended = true;
+			} else if !options_ended && w.starts_with("--") {
+				value_of = self.arguments.iter().find(|a| a.kind == ArgumentKind::Option && a.name == w[2..]);
+			} else {
+				positional.next();
+			}
+		}
+
+		let argument = value_of.or_else(|| positional.next());
+		let mut candidates: Vec<(String, Option<&str>)> = match argument.and_then(|a| a.completion.as_ref()) {
+			Some(completion) => completion.complete(&word.value).into_iter().map(|v| (v, None)).collect(),
+			None => Vec::new()
+		};
+
+		if value_of.is_none() && !options_ended {
+			candidates.extend(self.get_option_names());
+		}
+
+		candidates
+	}
+
+	/// Parse the arguments of a command line. Options and flags can be given anywhere,
+	/// the words after `--` are always positional. Values with spaces are quoted, see
+	/// `tokenize_arguments`.
+	pub fn parse(&self, args: &str) -> Result<ParsedArguments, ArgumentError> {
+		let mut parsed = ParsedArguments::default();
+		let mut positional = self.arguments.iter().filter(|a| a.kind == ArgumentKind::Positional);
+		let mut words = tokenize_arguments(args);
+		let mut options_ended = false;
+
+		while let Some(word) = words.next() {
+			if !options_ended && word == "--" {
+				options_ended = true;
+			} else if !options_ended && word.starts_with("--") {
+				let (name, inline_value) = match word.find('=') {
+					Some(i) => (&word[2..i], Some(&word[i + 1..])),
+					None => (&word[2..], None)
+				};
+
+				let argument = self.arguments.iter()
+					.find(|a| a.kind != ArgumentKind::Positional && a.name == name)
+					.ok_or_else(|| ArgumentError::UnknownOption { name: format!("--{}", name) })?;
+
+				let value: Cow<str> = match (argument.kind, inline_value) {
+					(ArgumentKind::Flag, None) => "".into(),
+					(ArgumentKind::Flag, Some(_)) => {
+						return Err(ArgumentError::Unexpected { arg: word.clone() });
+					},
+					(_, Some(value)) => value.into(),
+					(_, None) => {
+						words.next().ok_or_else(|| ArgumentError::MissingValue { name: argument.get_display_name() })?.into()
+					}
+				};
+
+				let value = if argument.kind == ArgumentKind::Flag {
+					ParsedValue { name: argument.name.to_string(), raw: "".into(), value: None }
+				} else {
+					argument.parse_value(&value)?
+				};
+				parsed.values.push(value);
+			} else {
+				let argument = positional.next().ok_or_else(|| ArgumentError::Unexpected { arg: word.clone() })?;
+				parsed.values.push(argument.parse_value(&word)?);
+			}
+		}
+
+		if let Some(missing) = self.arguments.iter().find(|a| a.required && !parsed.has(&a.name)) {
+			return Err(ArgumentError::Missing { name: missing.get_display_name() });
+		}
+
+		Ok(parsed)
+	}
+}
+
+struct ParsedValue {
+	name: String,
+	raw: String,
+	value: Option<Box<Any>>
+}
+
+/// The arguments of a command, parsed by its `ArgumentSchema`
+#[derive(Default)]
+pub struct ParsedArguments {
+	values: Vec<ParsedValue>
+}
+
+impl ParsedArguments {
+	fn find(&self, name: &str) -> Option<&ParsedValue> {
+		// the last occurrence of a repeated option wins
+		self.values.iter().rev().find(|v| v.name == name)
+	}
+
+	/// Was the argument given? Used for the flags.
+	pub fn has(&self, name: &str) -> bool {
+		self.find(name).is_some()
+	}
+
+	/// The value of the argument as it was typed
+	pub fn get_str(&self, name: &str) -> Option<&str> {
+		self.find(name).map(|v| v.raw.as_str())
+	}
+
+	/// The value of the argument, parsed by its input parser. The arguments without one are
+	/// `String`s. `None` if the argument wasn't given or if it isn't of the type `T`.
+	pub fn get<T: Clone + 'static>(&self, name: &str) -> Option<T> {
+		let v = self.find(name)?;
+		match v.value {
+			Some(ref value) => value.downcast_ref::<T>().cloned(),
+			None => (&v.raw as &Any).downcast_ref::<T>().cloned()
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_argument_schema() {
+		let schema = ArgumentSchema::new()
+			.arg(Argument::positional("ssid"))
+			.arg(Argument::positional("password").optional())
+			.arg(Argument::flag("hidden"))
+			.arg(Argument::option("channel").value(validate_property_min_max(1u8, 13)));
+
+		assert_eq!("<ssid> [<password>] [--hidden] [--channel <channel>]", schema.usage());
+
+		let args = schema.parse("--hidden home secret --channel=11").unwrap();
+		assert_eq!(Some("home".to_string()), args.get("ssid"));
+		assert_eq!(Some("secret"), args.get_str("password"));
+		assert_eq!(Some(11u8), args.get("channel"));
+		assert_eq!(None, args.get::<u32>("channel"));
+		assert!(args.has("hidden"));
+
+		let args = schema.parse("-- --home").unwrap();
+		assert_eq!(Some("--home"), args.get_str("ssid"));
+		assert!(!args.has("password"));
+
+		assert_eq!(Err(ArgumentError::Missing { name: "<ssid>".into() }), schema.parse("--hidden").map(|_| ()));
+		assert_eq!(Err(ArgumentError::Unexpected { arg: "more".into() }), schema.parse("a b more").map(|_| ()));
+		assert_eq!(Err(ArgumentError::UnknownOption { name: "--fast".into() }), schema.parse("a --fast").map(|_| ()));
+		assert_eq!(Err(ArgumentError::MissingValue { name: "--channel".into() }), schema.parse("a --channel").map(|_| ()));
+		assert_eq!(Err(ArgumentError::InvalidValue { name: "--channel".into(), value: "x".into() }), schema.parse("a --channel x").map(|_| ()));
+		assert_eq!(Err(ArgumentError::ValueTooBig { name: "--channel".into(), val: "14".into(), max: "13".into() }), schema.parse("a --channel 14").map(|_| ()));
+	}
+}
@@ -1,19 +1,145 @@
 use prelude::v1::*;
 use terminal::*;
+use cli_args::*;
+use tokenizer::*;
+
+/// How the typed characters are shown while a command is reading a line of input
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum InputEcho {
+	/// Show the characters as they are typed
+	Visible,
+	/// Show the mask character in place of every typed character
+	Masked(char),
+	/// Don't show anything
+	Hidden
+}
+
+/// A line of input requested by a command
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputRequest {
+	/// Printed in place of the usual prompt
+	pub prompt: String,
+	pub echo: InputEcho
+}
+
+/// A question asked by a command, answered by the user on the next line
+#[derive(Debug, Clone, PartialEq)]
+pub enum Question {
+	/// A line of free text, also see `CommandContext::request_input`
+	Input(InputRequest),
+	/// A yes or no question, an empty line selects the default answer
+	Confirm { text: String, default: bool },
+	/// Pick one of the listed choices, either by its number or by its name
+	Choice { text: String, choices: Vec<String> }
+}
+
+impl Question {
+	/// How the answer is shown while it is typed
+	pub fn get_echo(&self) -> InputEcho {
+		match *self {
+			Question::Input(ref request) => request.echo,
+			_ => InputEcho::Visible
+		}
+	}
+}
+
+/// The user's answer to a `Question`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Answer {
+	Input(String),
+	Confirm(bool),
+	/// Index of the selected choice
+	Choice(usize)
+}
+
+/// Handles the answer to a question, in place of executing the command's line again
+pub type AnswerHandler = Box<FnOnce(Answer, &mut CommandContext)>;
+
+/// A question asked by a command, waiting for the answer
+pub struct AskedQuestion {
+	question: Question,
+	/// Arguments of the command that asked
+	args: String,
+	/// Answers given to the command's earlier questions
+	answers: Vec<Answer>,
+	handler: AnswerHandler
+}
+
+impl AskedQuestion {
+	pub fn get_question(&self) -> &Question {
+		&self.question
+	}
+
+	/// Pass the answer to the command's handler. Returns the next question, if the handler
+	/// asked one.
+	pub fn answer(self, answer: Answer, terminal: &mut CharacterTerminalWriter, current_path: &str) -> Option<AskedQuestion> {
+		let mut answers = self.answers;
+		answers.push(answer.clone());
+
+		let mut question = None;
+		let mut failed = false;
+		{
+			let mut ctx = CommandContext {
+				args: self.args.into(),
+				terminal: terminal,
+				current_path: current_path,
+				answers: &answers,
+				question: &mut question,
+				failed: &mut failed,
+				arguments: Default::default()
+			};
+			(self.handler)(answer, &mut ctx);
+		}
+
+		question
+	}
+}
 
 /// Context for the execution of the command
 pub struct CommandContext<'b> {
 	pub args: Cow<'b, str>,
 	pub terminal: &'b mut CharacterTerminalWriter,
-	pub current_path: &'b str
+	pub current_path: &'b str,
+	/// Answers given so far to this command's questions
+	answers: &'b [Answer],
+	question: &'b mut Option<AskedQuestion>,
+	failed: &'b mut bool,
+	/// Arguments parsed by the command's `ArgumentSchema`, empty without one
+	pub arguments: ParsedArguments
 }
 
 impl<'b> CommandContext<'b> {
+	/// The context of a command executed from its line, before any question was answered
+	pub(crate) fn new(args: Cow<'b, str>, terminal: &'b mut CharacterTerminalWriter, current_path: &'b str,
+	                  question: &'b mut Option<AskedQuestion>, failed: &'b mut bool) -> CommandContext<'b> {
+		CommandContext {
+			args: args,
+			terminal: terminal,
+			current_path: current_path,
+			answers: &[],
+			question: question,
+			failed: failed,
+			arguments: Default::default()
+		}
+	}
+
 	#[inline]
 	pub fn get_args(&self) -> &str {
 		&self.args
 	}
 
+	/// The words of the arguments, with the quotes and the escapes removed
+	#[inline]
+	pub fn iter_args<'a>(&'a self) -> ArgumentTokens<'a> {
+		tokenize_arguments(&self.args)
+	}
+
+	/// Arguments parsed by the schema given to `CliContext::command_with_args`
+	#[inline]
+	pub fn get_arguments(&self) -> &ParsedArguments {
+		&self.arguments
+	}
+
 	#[inline]
 	pub fn get_terminal(&mut self) -> &mut CharacterTerminalWriter {
 		self.terminal
@@ -23,4 +149,113 @@ impl<'b> CommandContext<'b> {
 	pub fn get_current_path(&self) -> &str {
 		&self.current_path
 	}
+
+	/// Lines that were entered in response to this command's input requests, oldest
+	/// first. Empty until the first answer handler is called.
+	pub fn get_inputs(&self) -> Vec<&'b str> {
+		self.answers.iter().filter_map(|a| {
+			match *a {
+				Answer::Input(ref input) => Some(input.as_str()),
+				_ => None
+			}
+		}).collect()
+	}
+
+	/// Answers to this command's questions, oldest first. In an answer handler, the last
+	/// one is the answer being handled.
+	#[inline]
+	pub fn get_answers(&self) -> &'b [Answer] {
+		self.answers
+	}
+
+	/// Report that the command failed. One-shot executions exit with a non-zero code.
+	pub fn fail(&mut self) {
+		*self.failed = true;
+	}
+
+	/// Ask the user a question. The command should return after asking; once the
+	/// question is answered, only the handler is called with the answer, the command
+	/// line isn't executed again. The handler can ask the next question. Answers are
+	/// never recorded in the history and can't be autocompleted. Break cancels the
+	/// question.
+	///
+	/// The handler outlives the command, it has to own whatever it uses. Returns false,
+	/// without asking, if the question can't be answered: a choice without any choices.
+	pub fn ask<F>(&mut self, question: Question, handler: F) -> bool
+		where F: FnOnce(Answer, &mut CommandContext) + 'static
+	{
+		if let Question::Choice { ref choices, .. } = question {
+			if choices.is_empty() {
+				return false;
+			}
+		}
+
+		*self.question = Some(AskedQuestion {
+			question: question,
+			args: self.args.to_string(),
+			answers: self.answers.to_vec(),
+			handler: Box::new(handler)
+		});
+
+		true
+	}
+
+	/// Ask the user for a line of input, see `ask`. The handler is called with the
+	/// entered line.
+	pub fn request_input<F>(&mut self, prompt: &str, echo: InputEcho, handler: F)
+		where F: FnOnce(String, &mut CommandContext) + 'static
+	{
+		let request = InputRequest {
+			prompt: prompt.into(),
+			echo: echo
+		};
+
+		self.ask(Question::Input(request), move |answer, ctx| {
+			if let Answer::Input(input) = answer {
+				handler(input, ctx);
+			}
+		});
+	}
+
+	/// Ask for a password or a similar secret, masking the typed characters with `*`.
+	pub fn request_secret_input<F>(&mut self, prompt: &str, handler: F)
+		where F: FnOnce(String, &mut CommandContext) + 'static
+	{
+		self.request_input(prompt, InputEcho::Masked('*'), handler);
+	}
+
+	/// Ask a yes or no question, for example to confirm a destructive command. The
+	/// handler is called with the answer.
+	pub fn confirm<F>(&mut self, text: &str, default: bool, handler: F)
+		where F: FnOnce(bool, &mut CommandContext) + 'static
+	{
+		let question = Question::Confirm {
+			text: text.into(),
+			default: default
+		};
+
+		self.ask(question, move |answer, ctx| {
+			if let Answer::Confirm(yes) = answer {
+				handler(yes, ctx);
+			}
+		});
+	}
+
+	/// Ask the user to pick one of the choices. The handler is called with the index of
+	/// the selected choice. Returns false, without asking, if there is nothing to choose
+	/// from.
+	pub fn choose<F>(&mut self, text: &str, choices: &[&str], handler: F) -> bool
+		where F: FnOnce(usize, &mut CommandContext) + 'static
+	{
+		let question = Question::Choice {
+			text: text.into(),
+			choices: choices.iter().map(|c| c.to_string()).collect()
+		};
+
+		self.ask(question, move |answer, ctx| {
+			if let Answer::Choice(index) = answer {
+				handler(index, ctx);
+			}
+		})
+	}
 }
@@ -0,0 +1,90 @@
+use prelude::v1::*;
+use autocomplete::*;
+use cli::*;
+use terminal::*;
+use tokenizer::*;
+
+use i18n::*;
+
+/// Outcome of a non-interactive execution of a command line
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ExecutionResult {
+	/// The command was executed
+	Success,
+	/// The command reported a failure, or a property was given an invalid value
+	Failed,
+	/// The line didn't match any command or property
+	NotRecognized,
+	/// The command asked a question, which can only be answered at the prompt
+	InputRequired
+}
+
+impl ExecutionResult {
+	pub fn is_success(&self) -> bool {
+		*self == ExecutionResult::Success
+	}
+
+	/// Process exit code for the result. 0 on success, 2 for an unrecognized command
+	/// and 1 for the other failures.
+	pub fn exit_code(&self) -> i32 {
+		match *self {
+			ExecutionResult::Success => 0,
+			ExecutionResult::NotRecognized => 2,
+			ExecutionResult::Failed | ExecutionResult::InputRequired => 1
+		}
+	}
+}
+
+/// Execute a single command line without a prompt, for example one passed as the process
+/// arguments. Accepts the same closure as `PromptBuffer::handle_key`. Nothing is echoed,
+/// only the output of the command and the error messages are printed.
+pub fn execute_line<T, F>(line: &str, strings: &Strings, terminal: &mut T, call_commands: F) -> ExecutionResult
+	where T: CharacterTerminalWriter, F: FnOnce(&mut CliExecutor)
+{
+	let (result, question, failed) = {
+		let matcher = CliLineMatcher::new(line, LineMatcherMode::Execute);
+		let mut executor = CliExecutor::new(matcher, strings, terminal);
+		call_commands(&mut executor);
+		let question = executor.take_question();
+		let failed = executor.is_failed();
+		(executor.close().finish(), question, failed)
+	};
+
+	if let LineBufferResult::NoMatchFound = result {
+		if !line.trim().is_empty() {
+			strings.cmd_not_recognized(terminal, line.trim()).ok();
+			terminal.newline();
+		}
+		return ExecutionResult::NotRecognized;
+	}
+
+	if question.is_some() {
+		strings.question_requires_prompt(terminal).ok();
+		terminal.newline();
+		return ExecutionResult::InputRequired;
+	}
+
+	if failed {
+		ExecutionResult::Failed
+	} else {
+		ExecutionResult::Success
+	}
+}
+
+/// Join the process arguments into a command line, `mytool num1/set 5` becomes `num1/set 5`.
+/// The arguments should already exclude the program name. The arguments after the command
+/// are quoted when needed, so `mytool wifi/connect "home network"` keeps a single argument.
+pub fn args_to_line<I, S>(args: I) -> String
+	where I: IntoIterator<Item = S>, S: AsRef<str>
+{
+	let mut line = String::new();
+	for arg in args {
+		if line.is_empty() {
+			line.push_str(arg.as_ref());
+		} else {
+			line.push(' ');
+			line.push_str(&quote_argument(arg.as_ref(), None));
+		}
+	}
+	line
+}
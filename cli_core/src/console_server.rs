@@ -0,0 +1,613 @@
+use prelude::v1::*;
+use cli::*;
+use i18n::*;
+use keys::*;
+use keys_terminal::*;
+use prompt_buffer::*;
+use telnet::*;
+use terminal::*;
+
+use std::io::{ErrorKind, Read};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the blocked sockets check for a shutdown or an idle timeout
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Options for the remote console servers
+#[derive(Clone)]
+pub struct ConsoleServerOptions {
+	/// Options for the prompt of every session
+	pub prompt: PromptBufferOptions,
+	/// Maximum number of the connected clients, the others are turned away
+	pub max_sessions: usize,
+	/// Printed on a line of its own to every client right after it connects
+	pub banner: Cow<'static, str>,
+	/// Disconnect the clients that didn't send anything for this long
+	pub idle_timeout: Option<Duration>,
+	/// Messages of the server itself, like the one for a client over the session limit
+	pub strings: Arc<Strings + Send + Sync>
+}
+
+/// Options of the `TelnetServer`, shared with the other console servers
+pub type TelnetServerOptions = ConsoleServerOptions;
+
+impl Default for ConsoleServerOptions {
+	fn default() -> ConsoleServerOptions {
+		ConsoleServerOptions {
+			prompt: Default::default(),
+			max_sessions: 8,
+			banner: "".into(),
+			idle_timeout: None,
+			strings: Arc::new(English)
+		}
+	}
+}
+
+/// A connected client, passed to the commands along with the executor.
+pub struct ConsoleSession {
+	id: usize,
+	peer_addr: Option<SocketAddr>,
+	close_requested: bool
+}
+
+/// A client of the `TelnetServer`
+pub type TelnetSession = ConsoleSession;
+
+impl ConsoleSession {
+	/// Unique number of the session, counting from 1
+	pub fn get_id(&self) -> usize {
+		self.id
+	}
+
+	/// Network address of the client, `None` for the local sockets
+	pub fn get_peer_addr(&self) -> Option<SocketAddr> {
+		self.peer_addr
+	}
+
+	/// Disconnect the client after the current command
+	pub fn close(&mut self) {
+		self.close_requested = true;
+	}
+}
+
+/// Stops a running console server, can be sent to other threads and to the commands.
+#[derive(Clone)]
+pub struct ConsoleServerHandle {
+	shutdown: Arc<AtomicBool>
+}
+
+/// Stops a running `TelnetServer`
+pub type TelnetServerHandle = ConsoleServerHandle;
+
+impl ConsoleServerHandle {
+	fn new() -> ConsoleServerHandle {
+		ConsoleServerHandle { shutdown: Arc::new(AtomicBool::new(false)) }
+	}
+
+	/// Stop accepting the connections and disconnect all the clients. The `run` method
+	/// of the server returns once all the sessions are closed.
+	pub fn shutdown(&self) {
+		self.shutdown.store(true, Ordering::SeqCst);
+	}
+
+	/// Was the shutdown requested?
+	pub fn is_shutdown(&self) -> bool {
+		self.shutdown.load(Ordering::SeqCst)
+	}
+}
+
+/// Telnet console server, with a thread and a `PromptBuffer` for every connected client.
+/// All the sessions share the same command closure.
+pub struct TelnetServer {
+	listener: TcpListener,
+	options: ConsoleServerOptions,
+	handle: ConsoleServerHandle
+}
+
+impl TelnetServer {
+	/// Listen for the connections on the given address
+	pub fn bind<A: ToSocketAddrs>(addr: A, options: ConsoleServerOptions) -> io::Result<TelnetServer> {
+		Ok(TelnetServer {
+			listener: TcpListener::bind(addr)?,
+			options: options,
+			handle: ConsoleServerHandle::new()
+		})
+	}
+
+	/// The address the server is listening on
+	pub fn local_addr(&self) -> io::Result<SocketAddr> {
+		self.listener.local_addr()
+	}
+
+	/// Handle for stopping the server
+	pub fn get_handle(&self) -> ConsoleServerHandle {
+		self.handle.clone()
+	}
+
+	/// Accept the connections until the server is shut down.
+	pub fn run<F>(&self, commands: F) -> io::Result<()>
+		where F: Fn(&mut CliExecutor, &mut ConsoleSession) + Send + Sync + 'static
+	{
+		run_server(&self.listener, &self.options, &self.handle, true, commands)
+	}
+}
+
+/// Console server on a Unix domain socket, for attaching to a running service without
+/// opening a network port. The clients send the raw terminal input, as relayed by the
+/// `terminal_cli_client` binary, and report their window size as a cursor position report.
+/// The socket file is removed when the server is dropped.
+#[cfg(unix)]
+pub struct UnixConsoleServer {
+	listener: UnixListener,
+	path: PathBuf,
+	options: ConsoleServerOptions,
+	handle: ConsoleServerHandle
+}
+
+#[cfg(unix)]
+impl UnixConsoleServer {
+	/// Listen for the connections on the given socket path. Fails if the file already exists.
+	pub fn bind<P: AsRef<Path>>(path: P, options: ConsoleServerOptions) -> io::Result<UnixConsoleServer> {
+		Ok(UnixConsoleServer {
+			listener: UnixListener::bind(path.as_ref())?,
+			path: path.as_ref().to_path_buf(),
+			options: options,
+			handle: ConsoleServerHandle::new()
+		})
+	}
+
+	/// Path of the socket file
+	pub fn get_path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Handle for stopping the server
+	pub fn get_handle(&self) -> ConsoleServerHandle {
+		self.handle.clone()
+	}
+
+	/// Accept the connections until the server is shut down.
+	pub fn run<F>(&self, commands: F) -> io::Result<()>
+		where F: Fn(&mut CliExecutor, &mut ConsoleSession) + Send + Sync + 'static
+	{
+		run_server(&self.listener, &self.options, &self.handle, false, commands)
+	}
+}
+
+#[cfg(unix)]
+impl Drop for UnixConsoleServer {
+	fn drop(&mut self) {
+		let _ = ::std::fs::remove_file(&self.path);
+	}
+}
+
+/// The sockets the console servers can listen on
+trait ConsoleListener {
+	type Stream: ConsoleStream;
+
+	fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+	fn accept(&self) -> io::Result<(Self::Stream, Option<SocketAddr>)>;
+}
+
+trait ConsoleStream: Read + Write + Send + 'static {
+	fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+	fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+	fn shutdown(&self) -> io::Result<()>;
+}
+
+impl ConsoleListener for TcpListener {
+	type Stream = TcpStream;
+
+	fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+		TcpListener::set_nonblocking(self, nonblocking)
+	}
+
+	fn accept(&self) -> io::Result<(TcpStream, Option<SocketAddr>)> {
+		TcpListener::accept(self).map(|(stream, addr)| (stream, Some(addr)))
+	}
+}
+
+impl ConsoleStream for TcpStream {
+	fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+		TcpStream::set_nonblocking(self, nonblocking)
+	}
+
+	fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+		TcpStream::set_read_timeout(self, timeout)
+	}
+
+	fn shutdown(&self) -> io::Result<()> {
+		TcpStream::shutdown(self, Shutdown::Both)
+	}
+}
+
+#[cfg(unix)]
+impl ConsoleListener for UnixListener {
+	type Stream = UnixStream;
+
+	fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+		UnixListener::set_nonblocking(self, nonblocking)
+	}
+
+	fn accept(&self) -> io::Result<(UnixStream, Option<SocketAddr>)> {
+		UnixListener::accept(self).map(|(stream, _)| (stream, None))
+	}
+}
+
+#[cfg(unix)]
+impl ConsoleStream for UnixStream {
+	fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+		UnixStream::set_nonblocking(self, nonblocking)
+	}
+
+	fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+		UnixStream::set_read_timeout(self, timeout)
+	}
+
+	fn shutdown(&self) -> io::Result<()> {
+		UnixStream::shutdown(self, Shutdown::Both)
+	}
+}
+
+fn run_server<L, F>(listener: &L, options: &ConsoleServerOptions, handle: &ConsoleServerHandle, telnet: bool, commands: F) -> io::Result<()>
+	where L: ConsoleListener, F: Fn(&mut CliExecutor, &mut ConsoleSession) + Send + Sync + 'static
+{
+	let commands = Arc::new(commands);
+	let active_sessions = Arc::new(AtomicUsize::new(0));
+	let mut next_id = 1;
+	// the session threads, along with a flag set once they are done
+	let mut threads: Vec<(thread::JoinHandle<()>, Arc<AtomicBool>)> = vec![];
+
+	listener.set_nonblocking(true)?;
+
+	while !handle.is_shutdown() {
+		let (mut stream, peer_addr) = match listener.accept() {
+			Ok(connection) => connection,
+			Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
+				thread::sleep(POLL_INTERVAL);
+				continue;
+			},
+			Err(e) => { return Err(e); }
+		};
+
+		threads.retain(|t| !t.1.load(Ordering::SeqCst));
+
+		if active_sessions.load(Ordering::SeqCst) >= options.max_sessions {
+			let mut protocol = if telnet { Some(TelnetProtocol::new()) } else { None };
+			let mut output = SessionOutput::new();
+			options.strings.session_limit_reached(&mut output, options.max_sessions).ok();
+			output.newline();
+			let _ = stream.write_all(&output.encode(&mut protocol));
+			let _ = stream.shutdown();
+			continue;
+		}
+
+		active_sessions.fetch_add(1, Ordering::SeqCst);
+
+		let session = ConsoleSession {
+			id: next_id,
+			peer_addr: peer_addr,
+			close_requested: false
+		};
+		next_id += 1;
+
+		let options = options.clone();
+		let handle = handle.clone();
+		let commands = commands.clone();
+		let active_sessions = active_sessions.clone();
+		let finished = Arc::new(AtomicBool::new(false));
+		let thread_finished = finished.clone();
+
+		let thread = thread::spawn(move || {
+			let _guard = SessionGuard {
+				active_sessions: active_sessions,
+				finished: thread_finished
+			};
+			let _ = run_session(stream, session, &options, &handle, telnet, &*commands);
+		});
+		threads.push((thread, finished));
+	}
+
+	for (t, _) in threads {
+		let _ = t.join();
+	}
+
+	Ok(())
+}
+
+/// Releases the slot of a session once its thread is done, even if a command panicked
+struct SessionGuard {
+	active_sessions: Arc<AtomicUsize>,
+	finished: Arc<AtomicBool>
+}
+
+impl Drop for SessionGuard {
+	fn drop(&mut self) {
+		self.active_sessions.fetch_sub(1, Ordering::SeqCst);
+		self.finished.store(true, Ordering::SeqCst);
+	}
+}
+
+fn run_session<S, F>(mut stream: S, mut session: ConsoleSession, options: &ConsoleServerOptions, handle: &ConsoleServerHandle, telnet: bool, commands: &F) -> io::Result<()>
+	where S: ConsoleStream, F: Fn(&mut CliExecutor, &mut ConsoleSession)
+{
+	stream.set_nonblocking(false)?;
+	stream.set_read_timeout(Some(POLL_INTERVAL))?;
+
+	let mut prompt = PromptBuffer::new(options.prompt.clone());
+	let mut protocol = if telnet { Some(TelnetProtocol::new()) } else { None };
+	let mut decoder = TerminalKeyDecoder::new();
+	let mut last_input = Instant::now();
+
+	if let Some(ref mut protocol) = protocol {
+		stream.write_all(&protocol.hello())?;
+	}
+
+	{
+		let mut output = SessionOutput::new();
+		if !options.banner.is_empty() {
+			output.print_line(&options.banner);
+		}
+		prompt.print_prompt(&mut output);
+		stream.write_all(&output.encode(&mut protocol))?;
+	}
+
+	loop {
+		let mut buf = [0; 64];
+		let n = match stream.read(&mut buf) {
+			Ok(0) => { return Ok(()); },
+			Ok(n) => n,
+			Err(ref e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => 0,
+			Err(e) => { return Err(e); }
+		};
+
+		if handle.is_shutdown() {
+			let mut output = SessionOutput::new();
+			output.newline();
+			options.strings.server_shutting_down(&mut output).ok();
+			output.newline();
+			stream.write_all(&output.encode(&mut protocol))?;
+			return stream.shutdown();
+		}
+
+		let mut keys = vec![];
+
+		if n == 0 {
+			if options.idle_timeout.map_or(false, |t| last_input.elapsed() >= t) {
+				let mut output = SessionOutput::new();
+				output.newline();
+				options.strings.session_idle_timeout(&mut output).ok();
+				output.newline();
+				stream.write_all(&output.encode(&mut protocol))?;
+				return stream.shutdown();
+			}
+
+			// the output went idle after a carriage return
+			if let Some(ref mut protocol) = protocol {
+				let mut output = vec![];
+				protocol.flush_output(&mut output);
+				stream.write_all(&output)?;
+			}
+
+			// the input went idle in the middle of a sequence
+			keys.extend(decoder.flush());
+		} else {
+			last_input = Instant::now();
+
+			let input = match protocol {
+				Some(ref mut protocol) => protocol.handle_input(&buf[..n]),
+				None => TelnetInput { data: buf[..n].to_vec(), ..Default::default() }
+			};
+			stream.write_all(&input.response)?;
+
+			// a client that refused the server side echo echoes the input on its own
+			if let Some(ref protocol) = protocol {
+				prompt.set_echo(options.prompt.echo && protocol.is_echo_enabled());
+			}
+
+			if let Some((columns, rows)) = input.window_size {
+				prompt.set_terminal_size(columns, rows);
+			}
+
+			keys.extend(input.data.into_iter().filter_map(|b| decoder.decode(b).ok()));
+		}
+
+		for key in keys {
+			let mut output = SessionOutput::new();
+			let event = prompt.handle_key(key, &mut output, |m| commands(m, &mut session));
+			stream.write_all(&output.encode(&mut protocol))?;
+
+			if event == PromptEvent::Break || session.close_requested {
+				return stream.shutdown();
+			}
+		}
+	}
+}
+
+/// Collects the output of a single key press, for sending it in one packet
+struct SessionOutput {
+	buffer: Vec<u8>
+}
+
+impl SessionOutput {
+	fn new() -> SessionOutput {
+		SessionOutput { buffer: vec![] }
+	}
+
+	/// The output as sent over the network, encoded by the telnet protocol if there is one
+	fn encode(&self, protocol: &mut Option<TelnetProtocol>) -> Vec<u8> {
+		match *protocol {
+			Some(ref mut protocol) => {
+				let mut output = vec![];
+				protocol.encode_output(&self.buffer, &mut output);
+				output
+			},
+			None => self.buffer.clone()
+		}
+	}
+}
+
+impl CharacterTerminalWriter for SessionOutput {
+	fn print(&mut self, bytes: &[u8]) {
+		self.buffer.extend_from_slice(bytes);
+	}
+}
+
+impl FmtWrite for SessionOutput {
+	fn write_str(&mut self, s: &str) -> Result<(), FmtError> {
+		self.print(s.as_bytes());
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Read;
+
+	/// Read from the stream until the output contains the expected text
+	fn read_until<S: Read>(stream: &mut S, expected: &str) -> String {
+		let deadline = Instant::now() + Duration::from_secs(5);
+		let mut received = vec![];
+
+		while Instant::now() < deadline {
+			let mut buf = [0; 256];
+			match stream.read(&mut buf) {
+				Ok(0) => break,
+				Ok(n) => received.extend_from_slice(&buf[..n]),
+				Err(_) => ()
+			}
+
+			if String::from_utf8_lossy(&received).contains(expected) {
+				break;
+			}
+		}
+
+		String::from_utf8_lossy(&received).into_owned()
+	}
+
+	#[test]
+	fn test_telnet_server() {
+		let options = ConsoleServerOptions {
+			max_sessions: 1,
+			banner: "Welcome".into(),
+			..Default::default()
+		};
+		let server = TelnetServer::bind("127.0.0.1:0", options).unwrap();
+		let addr = server.local_addr().unwrap();
+		let handle = server.get_handle();
+
+		let server_thread = thread::spawn(move || {
+			server.run(|m, session| {
+				if let Some(mut ctx) = m.command("whoami") {
+					ctx.get_terminal().print_line(&format!("Session {}", session.get_id()));
+				}
+				if m.command("exit").is_some() {
+					session.close();
+				}
+				if m.command("panic").is_some() {
+					panic!("command failed");
+				}
+			})
+		});
+
+		let mut first = TcpStream::connect(addr).unwrap();
+		first.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+		assert!(read_until(&mut first, "# ").contains("Welcome\r\n# "));
+
+		let mut second = TcpStream::connect(addr).unwrap();
+		second.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+		assert!(read_until(&mut second, "sessions").contains("Too many sessions"));
+
+		first.write_all(b"whoami\r\n").unwrap();
+		assert!(read_until(&mut first, "Session 1").contains("Session 1"));
+
+		first.write_all(b"exit\r\n").unwrap();
+		read_until(&mut first, "never");
+
+		let mut third = TcpStream::connect(addr).unwrap();
+		third.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+		assert!(read_until(&mut third, "# ").contains("Welcome"));
+
+		// a panicking command ends its session, the slot is free again
+		third.write_all(b"panic\r\n").unwrap();
+		read_until(&mut third, "never");
+
+		let mut third = TcpStream::connect(addr).unwrap();
+		third.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+		assert!(read_until(&mut third, "# ").contains("Welcome"));
+
+		handle.shutdown();
+		assert!(read_until(&mut third, "shutting down").contains("shutting down"));
+		server_thread.join().unwrap().unwrap();
+	}
+
+	#[test]
+	fn test_telnet_server_client_echo() {
+		let server = TelnetServer::bind("127.0.0.1:0", Default::default()).unwrap();
+		let addr = server.local_addr().unwrap();
+		let handle = server.get_handle();
+
+		let server_thread = thread::spawn(move || {
+			server.run(|m, _| {
+				if let Some(mut ctx) = m.command("ping") {
+					ctx.get_terminal().print_line("Pong");
+				}
+			})
+		});
+
+		let mut client = TcpStream::connect(addr).unwrap();
+		client.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+		read_until(&mut client, "# ");
+
+		// IAC DONT ECHO, the client echoes the typed characters on its own
+		client.write_all(&[255, 254, 1]).unwrap();
+		client.write_all(b"ping\r\n").unwrap();
+		let output = read_until(&mut client, "Pong");
+		assert!(output.contains("Pong"));
+		assert!(!output.contains("ping"));
+
+		handle.shutdown();
+		read_until(&mut client, "shutting down");
+		server_thread.join().unwrap().unwrap();
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn test_unix_console_server() {
+		let path = ::std::env::temp_dir().join(format!("terminal_cli_test_{}.sock", ::std::process::id()));
+		let _ = ::std::fs::remove_file(&path);
+
+		let server = UnixConsoleServer::bind(&path, Default::default()).unwrap();
+		let handle = server.get_handle();
+
+		let server_thread = thread::spawn(move || {
+			server.run(|m, session| {
+				if let Some(mut ctx) = m.command("hello") {
+					ctx.get_terminal().print_line(&format!("Hello {:?}", session.get_peer_addr()));
+				}
+			})
+		});
+
+		let mut client = UnixStream::connect(&path).unwrap();
+		client.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+		read_until(&mut client, "# ");
+
+		// the window size as the client reports it, then autocomplete
+		client.write_all(b"\x1b[8;24;40the\t\r").unwrap();
+		assert!(read_until(&mut client, "Hello None").contains("hello\r\nHello None"));
+
+		handle.shutdown();
+		read_until(&mut client, "shutting down");
+		server_thread.join().unwrap().unwrap();
+		assert!(!path.exists());
+	}
+}
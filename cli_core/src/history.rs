@@ -0,0 +1,315 @@
+use prelude::v1::*;
+
+/// Bounded list of previously entered lines, navigable with the Up and Down keys.
+/// The oldest entries are discarded once the capacity is reached.
+pub struct LineHistory {
+	entries: VecDeque<String>,
+	capacity: usize,
+	ignore_duplicates: bool,
+	ignore_space: bool,
+	position: Option<usize>,
+	draft: Option<String>
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HistoryStoreError {
+	Error,
+	Full
+}
+
+/// Persistent storage for the line history. Implement it to keep the history across
+/// sessions, for example in a file or in a flash page on an embedded device.
+pub trait HistoryStore {
+	/// Load all the stored lines, oldest first
+	fn load(&mut self) -> Result<Vec<String>, HistoryStoreError>;
+
+	/// Append a newly executed line
+	fn append(&mut self, line: &str) -> Result<(), HistoryStoreError>;
+}
+
+/// Keeps the history in memory, discarding the oldest lines once `max_lines` is reached.
+pub struct MemoryHistoryStore {
+	lines: VecDeque<String>,
+	max_lines: usize
+}
+
+impl MemoryHistoryStore {
+	pub fn new(max_lines: usize) -> MemoryHistoryStore {
+		MemoryHistoryStore {
+			lines: VecDeque::new(),
+			max_lines: max_lines
+		}
+	}
+}
+
+impl HistoryStore for MemoryHistoryStore {
+	fn load(&mut self) -> Result<Vec<String>, HistoryStoreError> {
+		Ok(self.lines.iter().cloned().collect())
+	}
+
+	fn append(&mut self, line: &str) -> Result<(), HistoryStoreError> {
+		if self.max_lines == 0 {
+			return Err(HistoryStoreError::Full);
+		}
+
+		while self.lines.len() >= self.max_lines {
+			self.lines.pop_front();
+		}
+
+		self.lines.push_back(line.to_string());
+		Ok(())
+	}
+}
+
+/// Stores the history in a text file, one line per entry. The file is rewritten
+/// without the oldest lines once `max_lines` is reached, pass the same value as
+/// `PromptBufferOptions::history_size` to keep the whole history of the prompt.
+#[cfg(feature="std")]
+pub struct FileHistoryStore {
+	path: ::std::path::PathBuf,
+	max_lines: usize
+}
+
+#[cfg(feature="std")]
+impl FileHistoryStore {
+	pub fn new<P: Into<::std::path::PathBuf>>(path: P, max_lines: usize) -> FileHistoryStore {
+		FileHistoryStore {
+			path: path.into(),
+			max_lines: max_lines
+		}
+	}
+}
+
+#[cfg(feature="std")]
+impl HistoryStore for FileHistoryStore {
+	fn load(&mut self) -> Result<Vec<String>, HistoryStoreError> {
+		use std::io::{BufRead, BufReader, ErrorKind};
+
+		let file = match ::std::fs::File::open(&self.path) {
+			Ok(file) => file,
+			Err(ref e) if e.kind() == ErrorKind::NotFound => { return Ok(vec![]); },
+			Err(_) => { return Err(HistoryStoreError::Error); }
+		};
+
+		BufReader::new(file).lines()
+			.collect::<Result<Vec<_>, _>>()
+			.map_err(|_| HistoryStoreError::Error)
+	}
+
+	fn append(&mut self, line: &str) -> Result<(), HistoryStoreError> {
+		if self.max_lines == 0 {
+			return Err(HistoryStoreError::Full);
+		}
+
+		let lines = self.load()?;
+		if lines.len() >= self.max_lines {
+			let mut file = ::std::fs::File::create(&self.path).map_err(|_| HistoryStoreError::Error)?;
+			for l in lines.iter().skip(lines.len() + 1 - self.max_lines) {
+				writeln!(file, "{}", l).map_err(|_| HistoryStoreError::Error)?;
+			}
+
+			return writeln!(file, "{}", line).map_err(|_| HistoryStoreError::Error);
+		}
+
+		let mut file = ::std::fs::OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(&self.path)
+			.map_err(|_| HistoryStoreError::Error)?;
+
+		writeln!(file, "{}", line).map_err(|_| HistoryStoreError::Error)
+	}
+}
+
+impl LineHistory {
+	/// Create a new history holding at most `capacity` lines. A capacity of zero
+	/// disables the history.
+	pub fn new(capacity: usize) -> LineHistory {
+		LineHistory {
+			entries: VecDeque::new(),
+			capacity: capacity,
+			ignore_duplicates: true,
+			ignore_space: false,
+			position: None,
+			draft: None
+		}
+	}
+
+	/// Don't store a line identical to the previous one. Enabled by default.
+	pub fn set_ignore_duplicates(&mut self, ignore: bool) {
+		self.ignore_duplicates = ignore;
+	}
+
+	/// Don't store lines starting with a space. Disabled by default.
+	pub fn set_ignore_space(&mut self, ignore: bool) {
+		self.ignore_space = ignore;
+	}
+
+	/// Append an executed line. Empty lines and the lines filtered out by the ignore
+	/// settings are skipped. Resets the navigation state. Returns true if the line was stored.
+	pub fn push(&mut self, line: &str) -> bool {
+		self.reset_navigation();
+
+		if self.capacity == 0 || line.trim().is_empty() {
+			return false;
+		}
+
+		if self.ignore_space && line.starts_with(' ') {
+			return false;
+		}
+
+		if self.ignore_duplicates && self.entries.back().map(|l| l.as_str()) == Some(line) {
+			return false;
+		}
+
+		while self.entries.len() >= self.capacity {
+			self.entries.pop_front();
+		}
+
+		self.entries.push_back(line.to_string());
+		true
+	}
+
+	/// Step to an older entry. The `current` line is remembered when leaving the
+	/// line that is being edited, so it can be restored by stepping back down.
+	pub fn older(&mut self, current: &str) -> Option<&str> {
+		let position = match self.position {
+			None => {
+				if self.entries.is_empty() {
+					return None;
+				}
+				self.draft = Some(current.to_string());
+				self.entries.len() - 1
+			},
+			Some(0) => {
+				return None;
+			},
+			Some(p) => p - 1
+		};
+
+		self.position = Some(position);
+		self.entries.get(position).map(|s| s.as_str())
+	}
+
+	/// Step to a newer entry. Past the newest entry, the line that was being edited
+	/// before the navigation started is returned.
+	pub fn newer(&mut self) -> Option<&str> {
+		match self.position {
+			None => None,
+			Some(p) if p + 1 < self.entries.len() => {
+				self.position = Some(p + 1);
+				self.entries.get(p + 1).map(|s| s.as_str())
+			},
+			Some(_) => {
+				self.position = None;
+				self.draft.as_ref().map(|s| s.as_str())
+			}
+		}
+	}
+
+	/// Stop the navigation, the next `older` call starts at the newest entry.
+	pub fn reset_navigation(&mut self) {
+		self.position = None;
+		self.draft = None;
+	}
+
+	/// Is the user currently browsing through the history?
+	pub fn is_navigating(&self) -> bool {
+		self.position.is_some()
+	}
+
+	/// Find the newest line that contains `query` and is older than the entry at
+	/// index `before`. Returns the line's index along with the line.
+	pub fn search(&self, query: &str, before: usize) -> Option<(usize, &str)> {
+		let before = min(before, self.entries.len());
+		self.entries.iter().enumerate().take(before).rev()
+			.find(|&(_, line)| line.contains(query))
+			.map(|(i, line)| (i, line.as_str()))
+	}
+
+	/// Number of stored lines
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	/// Is the history empty?
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// Remove all the stored lines
+	pub fn clear(&mut self) {
+		self.reset_navigation();
+		self.entries.clear();
+	}
+
+	/// Stored lines, oldest first
+	pub fn iter<'a>(&'a self) -> impl Iterator<Item = &'a str> + 'a {
+		self.entries.iter().map(|s| s.as_str())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_history_navigation() {
+		let mut history = LineHistory::new(2);
+		history.push("first");
+		history.push("");
+		history.push("second");
+		history.push("third");
+		assert_eq!(vec!["second", "third"], history.iter().collect::<Vec<_>>());
+
+		assert_eq!(Some("third"), history.older("typed"));
+		assert_eq!(Some("second"), history.older("third"));
+		assert_eq!(None, history.older("second"));
+		assert_eq!(Some("third"), history.newer());
+		assert_eq!(Some("typed"), history.newer());
+		assert_eq!(None, history.newer());
+		assert!(!history.is_navigating());
+	}
+
+	#[test]
+	fn test_history_search() {
+		let mut history = LineHistory::new(10);
+		history.push("p1/hello");
+		history.push("counter/set 5");
+		history.push("p2/hello");
+
+		assert_eq!(Some((2, "p2/hello")), history.search("hello", 3));
+		assert_eq!(Some((0, "p1/hello")), history.search("hello", 2));
+		assert_eq!(None, history.search("hello", 0));
+		assert_eq!(Some((1, "counter/set 5")), history.search("set", 100));
+	}
+
+	#[test]
+	fn test_history_filters() {
+		let mut history = LineHistory::new(10);
+		history.set_ignore_space(true);
+		assert!(history.push("a"));
+		assert!(!history.push("a"));
+		assert!(!history.push(" secret"));
+		assert!(history.push("b"));
+		assert!(history.push("a"));
+		assert_eq!(vec!["a", "b", "a"], history.iter().collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn test_file_history_store() {
+		let path = ::std::env::temp_dir().join(format!("terminal_cli_history_{}", ::std::process::id()));
+		let _ = ::std::fs::remove_file(&path);
+
+		let mut store = FileHistoryStore::new(path.clone(), 2);
+		assert_eq!(Ok(vec![]), store.load());
+		store.append("p1/hello").unwrap();
+		store.append("counter/set 5").unwrap();
+		assert_eq!(Ok(vec!["p1/hello".to_string(), "counter/set 5".to_string()]), FileHistoryStore::new(path.clone(), 2).load());
+
+		store.append("p2/hello").unwrap();
+		assert_eq!(Ok(vec!["counter/set 5".to_string(), "p2/hello".to_string()]), store.load());
+
+		::std::fs::remove_file(&path).unwrap();
+	}
+}
@@ -2,6 +2,8 @@
 
 use prelude::v1::*;
 use terminal::CharacterTerminalWriter;
+use cli_command::Question;
+use cli_args::ArgumentError;
 
 pub trait Strings {
     fn property_invalid_value(&self, f: &mut CharacterTerminalWriter, id: &str, input: &str) -> Result<(), FmtError> {
@@ -20,6 +22,93 @@ pub trait Strings {
         write!(f, "New value for {} is {}.", id, val)
     }
 
+    fn history_search(&self, f: &mut CharacterTerminalWriter, query: &str, line: &str, found: bool) -> Result<(), FmtError> {
+        if found {
+            write!(f, "(reverse-i-search)'{}': {}", query, line)
+        } else {
+            write!(f, "(failed reverse-i-search)'{}': {}", query, line)
+        }
+    }
+
+    fn line_too_long(&self, f: &mut CharacterTerminalWriter, max_length: usize) -> Result<(), FmtError> {
+        write!(f, "Line is too long, the maximum length is {} characters.", max_length)
+    }
+
+    /// The prompt line for a question
+    fn question_prompt(&self, f: &mut CharacterTerminalWriter, question: &Question) -> Result<(), FmtError> {
+        match *question {
+            Question::Input(ref request) => write!(f, "{}", request.prompt),
+            Question::Confirm { ref text, default: true } => write!(f, "{} [Y/n] ", text),
+            Question::Confirm { ref text, default: false } => write!(f, "{} [y/N] ", text),
+            Question::Choice { ref choices, .. } => write!(f, "Choice [1-{}]: ", choices.len())
+        }
+    }
+
+    /// Printed once when the question is asked, before the prompt line
+    fn question_intro(&self, f: &mut CharacterTerminalWriter, question: &Question) -> Result<(), FmtError> {
+        if let Question::Choice { ref text, ref choices } = *question {
+            write!(f, "{}", text)?;
+            f.newline();
+            for (i, choice) in choices.iter().enumerate() {
+                write!(f, "  {}) {}", i + 1, choice)?;
+                f.newline();
+            }
+        }
+        Ok(())
+    }
+
+    fn question_invalid_answer(&self, f: &mut CharacterTerminalWriter, question: &Question) -> Result<(), FmtError> {
+        match *question {
+            Question::Confirm { .. } => write!(f, "Please answer yes or no."),
+            _ => write!(f, "Please pick one of the listed choices.")
+        }
+    }
+
+    /// Parse the answer to a yes or no question
+    fn parse_confirmation(&self, answer: &str) -> Option<bool> {
+        match answer.to_lowercase().as_str() {
+            "y" | "yes" => Some(true),
+            "n" | "no" => Some(false),
+            _ => None
+        }
+    }
+
+    fn path_not_found(&self, f: &mut CharacterTerminalWriter, path: &str) -> Result<(), FmtError> {
+        write!(f, "Path '{}' not found.", path)
+    }
+
+    fn session_limit_reached(&self, f: &mut CharacterTerminalWriter, max_sessions: usize) -> Result<(), FmtError> {
+        write!(f, "Too many sessions, at most {} clients can be connected.", max_sessions)
+    }
+
+    fn session_idle_timeout(&self, f: &mut CharacterTerminalWriter) -> Result<(), FmtError> {
+        write!(f, "Disconnecting after a period of inactivity.")
+    }
+
+    fn server_shutting_down(&self, f: &mut CharacterTerminalWriter) -> Result<(), FmtError> {
+        write!(f, "The server is shutting down.")
+    }
+
+    fn question_requires_prompt(&self, f: &mut CharacterTerminalWriter) -> Result<(), FmtError> {
+        write!(f, "The command needs an answer, run it from the interactive prompt.")
+    }
+
+    fn argument_error(&self, f: &mut CharacterTerminalWriter, error: &ArgumentError) -> Result<(), FmtError> {
+        match *error {
+            ArgumentError::Missing { ref name } => write!(f, "Missing the required argument {}.", name),
+            ArgumentError::Unexpected { ref arg } => write!(f, "Unexpected argument '{}'.", arg),
+            ArgumentError::UnknownOption { ref name } => write!(f, "Unknown option {}.", name),
+            ArgumentError::MissingValue { ref name } => write!(f, "Option {} requires a value.", name),
+            ArgumentError::InvalidValue { ref name, ref value } => self.property_invalid_value(f, name, value),
+            ArgumentError::ValueTooSmall { ref name, ref val, ref min } => self.property_value_too_small(f, name, val, min),
+            ArgumentError::ValueTooBig { ref name, ref val, ref max } => self.property_value_too_big(f, name, val, max)
+        }
+    }
+
+    fn command_usage(&self, f: &mut CharacterTerminalWriter, cmd: &str, usage: &str) -> Result<(), FmtError> {
+        write!(f, "Usage: {} {}", cmd, usage)
+    }
+
     fn cmd_not_recognized(&self, f: &mut CharacterTerminalWriter, cmd: &str) -> Result<(), FmtError> {
         write!(f, "Command not recognized.")
     }
@@ -8,17 +8,116 @@ pub enum DirectionKey {
     Right
 }
 
+/// Modifier keys held down along with a key
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct KeyModifiers {
+    pub shift: bool,
+    pub alt: bool,
+    pub ctrl: bool
+}
+
+impl KeyModifiers {
+    pub const SHIFT: KeyModifiers = KeyModifiers { shift: true, alt: false, ctrl: false };
+    pub const ALT: KeyModifiers = KeyModifiers { shift: false, alt: true, ctrl: false };
+    pub const CTRL: KeyModifiers = KeyModifiers { shift: false, alt: false, ctrl: true };
+
+    /// Decode the xterm modifier parameter of a CSI sequence, `5` in `ESC [ 1 ; 5 D`.
+    /// The Meta key is reported as Alt.
+    pub fn from_csi_parameter(parameter: u32) -> KeyModifiers {
+        let bits = parameter.saturating_sub(1);
+
+        KeyModifiers {
+            shift: bits & 1 != 0,
+            alt: bits & (2 | 8) != 0,
+            ctrl: bits & 4 != 0
+        }
+    }
+
+    /// Encode as the xterm modifier parameter of a CSI sequence
+    pub fn to_csi_parameter(&self) -> u32 {
+        1 + (self.shift as u32) + (self.alt as u32) * 2 + (self.ctrl as u32) * 4
+    }
+
+    /// Is no modifier held down?
+    pub fn is_empty(&self) -> bool {
+        !(self.shift || self.alt || self.ctrl)
+    }
+}
+
+/// Key that was pressed along with a modifier
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ModifiedKey {
+    Arrow(DirectionKey),
+    Backspace,
+    Delete,
+    Tab,
+    Home,
+    End,
+    Insert,
+    PageUp,
+    PageDown,
+    Function(u8),
+    Character(char)
+}
+
 /// Decoded key press
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Key {	
     Arrow(DirectionKey),
     Backspace,
+    Delete,
     Tab,
     Newline,
     CarriageReturn,
     Break,
     Eot,
-    Character(u8)
+    Home,
+    End,
+    Insert,
+    PageUp,
+    PageDown,
+    /// Function key, `Function(1)` to `Function(12)` for F1 to F12
+    Function(u8),
+    /// A standalone Escape key press. `TerminalKeyDecoder` reports it for an ESC that
+    /// isn't followed by the rest of an escape sequence, see `KeyDecoder::flush`.
+    Escape,
+    /// Key combined with Shift, Alt or Ctrl, for example `ESC [ 1 ; 5 D` for Ctrl+Left
+    /// or `ESC b` for Alt+b
+    Modified(KeyModifiers, ModifiedKey),
+    /// Control key combined with a letter, for example `Control('a')` for Ctrl-A
+    Control(char),
+    /// A printable character, decoded from UTF-8
+    Character(char),
+    /// The terminal's reply to a cursor position query, `ESC [ row ; column R`. Not a key
+    /// press, see `PromptBuffer::query_terminal_size`. A reply on the first row reads
+    /// the same as F3 with modifiers, `ESC [ 1 ; 5 R` is Ctrl+F3, and is decoded as one.
+    CursorPosition { row: u16, column: u16 },
+    /// The terminal's report of its text area size, `ESC [ 8 ; rows ; columns t`. Not a
+    /// key press, `PromptBuffer` uses it as its size.
+    WindowSize { rows: u16, columns: u16 }
+}
+
+impl Key {
+    /// Key pressed along with the modifiers, or the plain key if no modifiers are held down
+    pub fn modified(modifiers: KeyModifiers, key: ModifiedKey) -> Key {
+        if !modifiers.is_empty() {
+            return Key::Modified(modifiers, key);
+        }
+
+        match key {
+            ModifiedKey::Arrow(d) => Key::Arrow(d),
+            ModifiedKey::Backspace => Key::Backspace,
+            ModifiedKey::Delete => Key::Delete,
+            ModifiedKey::Tab => Key::Tab,
+            ModifiedKey::Home => Key::Home,
+            ModifiedKey::End => Key::End,
+            ModifiedKey::Insert => Key::Insert,
+            ModifiedKey::PageUp => Key::PageUp,
+            ModifiedKey::PageDown => Key::PageDown,
+            ModifiedKey::Function(n) => Key::Function(n),
+            ModifiedKey::Character(c) => Key::Character(c)
+        }
+    }
 }
 
 /// Key decoder error
@@ -31,4 +130,16 @@ pub enum KeyDecoderError {
 /// Key decoder trait
 pub trait KeyDecoder {
     fn decode(&mut self, byte: u8) -> Result<Key, KeyDecoderError>;
+
+    /// Is the decoder holding the start of an unfinished sequence, or a decoded key that
+    /// wasn't returned yet? Terminal backends should call `flush` if no further input
+    /// arrives within a short timeout.
+    fn is_pending(&self) -> bool {
+        false
+    }
+
+    /// Finish the pending sequence once the input went idle.
+    fn flush(&mut self) -> Option<Key> {
+        None
+    }
 }
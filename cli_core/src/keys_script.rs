@@ -0,0 +1,298 @@
+use prelude::v1::*;
+use keys::*;
+use keys_terminal::*;
+
+/// Key script error
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum KeyScriptError {
+    /// A `<` without the closing `>`, at the given byte offset of the script
+    UnclosedKey { position: usize },
+    /// Unknown key name in the brackets, at the given byte offset of the script
+    UnknownKey { position: usize },
+    /// The key can't be represented as terminal input
+    NotEncodable(Key)
+}
+
+/// Parse a script of key presses. Characters stand for themselves, special keys are
+/// written in angle brackets, optionally with modifiers: `<Tab>`, `<Up>`, `<Enter>`,
+/// `<F5>`, `<C-c>`, `<A-b>`, `<S-Tab>`, `<C-Left>`. Use `<lt>` for a literal `<`.
+///
+/// Control letters are decoded as a terminal would send them, so `<C-c>` is
+/// `Key::Break` and `<C-d>` is `Key::Eot`.
+///
+/// ```
+/// # use terminal_cli::*;
+/// let keys = parse_key_script("ab<Left><BS><Enter>").unwrap();
+/// assert_eq!(vec![Key::Character('a'), Key::Character('b'), Key::Arrow(DirectionKey::Left),
+///                 Key::Backspace, Key::Newline], keys);
+/// ```
+pub fn parse_key_script(script: &str) -> Result<Vec<Key>, KeyScriptError> {
+    let mut keys = vec![];
+    let mut position = 0;
+
+    while let Some(c) = script[position..].chars().next() {
+        let key = match c {
+            '<' => {
+                let name_start = position + 1;
+                let name_len = script[name_start..].find('>').ok_or(KeyScriptError::UnclosedKey { position: position })?;
+                let key = parse_key_name(&script[name_start..name_start + name_len]).ok_or(KeyScriptError::UnknownKey { position: position })?;
+                position = name_start + name_len + 1;
+                keys.push(key);
+                continue;
+            },
+            '\n' => Key::Newline,
+            '\r' => Key::CarriageReturn,
+            '\t' => Key::Tab,
+            c => Key::Character(c)
+        };
+
+        keys.push(key);
+        position += c.len_utf8();
+    }
+
+    Ok(keys)
+}
+
+/// Parse a key script and encode it into the bytes a terminal would send.
+pub fn key_script_to_bytes(script: &str) -> Result<Vec<u8>, KeyScriptError> {
+    encode_keys(&parse_key_script(script)?)
+}
+
+/// Encode the keys into terminal input, the inverse of `TerminalKeyDecoder`. With the
+/// decoder's default options, a few keys are read back differently, just as they would
+/// be from a terminal: a `Key::CarriageReturn` directly followed by a `Key::Newline`, or
+/// the other way round, is decoded as the first key of the pair, and `Key::Control('h')`
+/// is decoded as `Key::Backspace`. A `Key::Escape` followed by a key that starts with a
+/// printable byte, like `Key::Character('a')`, would be read back as Alt combined with
+/// that key, so it's `NotEncodable`.
+pub fn encode_keys(keys: &[Key]) -> Result<Vec<u8>, KeyScriptError> {
+    let mut bytes = vec![];
+    let mut previous = None;
+    for key in keys {
+        let start = bytes.len();
+        encode_key(*key, &mut bytes)?;
+        if let (Some(Key::Escape), Some(&(0x20..=0x7F))) = (previous, bytes.get(start)) {
+            return Err(KeyScriptError::NotEncodable(Key::Escape));
+        }
+        previous = Some(*key);
+    }
+    Ok(bytes)
+}
+
+/// Append the bytes a VT100/xterm compatible terminal sends for the key.
+pub fn encode_key(key: Key, bytes: &mut Vec<u8>) -> Result<(), KeyScriptError> {
+    match key {
+        Key::Arrow(_) | Key::Home | Key::End | Key::Insert | Key::Delete |
+        Key::PageUp | Key::PageDown | Key::Function(_) => {
+            let key = modified_key(key).ok_or(KeyScriptError::NotEncodable(key))?;
+            encode_sequence(key, KeyModifiers::default(), bytes);
+        },
+        Key::Backspace => bytes.push(0x7F),
+        Key::Tab => bytes.push(0x09),
+        Key::Newline => bytes.push(b'\n'),
+        Key::CarriageReturn => bytes.push(b'\r'),
+        Key::Break => bytes.push(0x03),
+        Key::Eot => bytes.push(0x04),
+        Key::Escape => bytes.push(0x1B),
+        Key::Control(c @ 'a'..='z') => bytes.push(c as u8 - b'a' + 0x01),
+        Key::Control(_) => {
+            return Err(KeyScriptError::NotEncodable(key));
+        },
+        Key::Character(c) => encode_char(c, bytes),
+        Key::CursorPosition { row, column } => {
+            bytes.extend_from_slice(format!("\x1b[{};{}R", row, column).as_bytes());
+        },
+        Key::WindowSize { rows, columns } => {
+            bytes.extend_from_slice(format!("\x1b[8;{};{}t", rows, columns).as_bytes());
+        },
+        Key::Modified(KeyModifiers { shift: true, alt: false, ctrl: false }, ModifiedKey::Tab) => {
+            bytes.extend_from_slice(b"\x1b[Z");
+        },
+        Key::Modified(KeyModifiers { shift: false, alt: true, ctrl: false }, ModifiedKey::Character(c)) => {
+            bytes.push(0x1B);
+            encode_char(c, bytes);
+        },
+        Key::Modified(KeyModifiers { shift: false, alt: true, ctrl: false }, ModifiedKey::Backspace) => {
+            bytes.extend_from_slice(b"\x1b\x7f");
+        },
+        Key::Modified(_, ModifiedKey::Tab) | Key::Modified(_, ModifiedKey::Character(_)) | Key::Modified(_, ModifiedKey::Backspace) => {
+            return Err(KeyScriptError::NotEncodable(key));
+        },
+        Key::Modified(modifiers, k) => {
+            encode_sequence(k, modifiers, bytes);
+        }
+    }
+
+    Ok(())
+}
+
+fn encode_char(c: char, bytes: &mut Vec<u8>) {
+    let mut buf = [0; 4];
+    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+}
+
+/// Encode a CSI or SS3 key sequence, with the modifiers as the second CSI parameter
+fn encode_sequence(key: ModifiedKey, modifiers: KeyModifiers, bytes: &mut Vec<u8>) {
+    let final_byte = match key {
+        ModifiedKey::Arrow(DirectionKey::Up) => b'A',
+        ModifiedKey::Arrow(DirectionKey::Down) => b'B',
+        ModifiedKey::Arrow(DirectionKey::Right) => b'C',
+        ModifiedKey::Arrow(DirectionKey::Left) => b'D',
+        ModifiedKey::Home => b'H',
+        ModifiedKey::End => b'F',
+        ModifiedKey::Function(n @ 1..=4) => b'P' + n - 1,
+        _ => b'~'
+    };
+
+    if final_byte != b'~' {
+        if modifiers.is_empty() {
+            let intro: &[u8] = if let ModifiedKey::Function(_) = key { b"\x1bO" } else { b"\x1b[" };
+            bytes.extend_from_slice(intro);
+        } else {
+            bytes.extend_from_slice(format!("\x1b[1;{}", modifiers.to_csi_parameter()).as_bytes());
+        }
+        bytes.push(final_byte);
+        return;
+    }
+
+    let code = match key {
+        ModifiedKey::Insert => 2,
+        ModifiedKey::Delete => 3,
+        ModifiedKey::PageUp => 5,
+        ModifiedKey::PageDown => 6,
+        ModifiedKey::Function(n @ 5) => n as u32 + 10,
+        ModifiedKey::Function(n @ 6..=10) => n as u32 + 11,
+        ModifiedKey::Function(n) => n as u32 + 12,
+        _ => 0
+    };
+
+    if modifiers.is_empty() {
+        bytes.extend_from_slice(format!("\x1b[{}~", code).as_bytes());
+    } else {
+        bytes.extend_from_slice(format!("\x1b[{};{}~", code, modifiers.to_csi_parameter()).as_bytes());
+    }
+}
+
+/// The key's representation when combined with modifiers
+fn modified_key(key: Key) -> Option<ModifiedKey> {
+    match key {
+        Key::Arrow(d) => Some(ModifiedKey::Arrow(d)),
+        Key::Backspace => Some(ModifiedKey::Backspace),
+        Key::Delete => Some(ModifiedKey::Delete),
+        Key::Tab => Some(ModifiedKey::Tab),
+        Key::Home => Some(ModifiedKey::Home),
+        Key::End => Some(ModifiedKey::End),
+        Key::Insert => Some(ModifiedKey::Insert),
+        Key::PageUp => Some(ModifiedKey::PageUp),
+        Key::PageDown => Some(ModifiedKey::PageDown),
+        Key::Function(n) => Some(ModifiedKey::Function(n)),
+        Key::Character(c) => Some(ModifiedKey::Character(c)),
+        _ => None
+    }
+}
+
+/// Parse the contents of the angle brackets, `C-Left` or `Tab`
+fn parse_key_name(name: &str) -> Option<Key> {
+    let mut modifiers = KeyModifiers::default();
+    let mut name = name;
+
+    while name.len() > 2 && name.as_bytes()[1] == b'-' {
+        match name.as_bytes()[0] {
+            b'C' | b'c' => modifiers.ctrl = true,
+            b'S' | b's' => modifiers.shift = true,
+            b'A' | b'a' | b'M' | b'm' => modifiers.alt = true,
+            _ => { return None; }
+        }
+        name = &name[2..];
+    }
+
+    let mut chars = name.chars();
+    let key = match (chars.next(), chars.next()) {
+        (Some(c), None) => Key::Character(c),
+        _ => {
+            match name.to_lowercase().as_str() {
+                "enter" | "return" | "nl" => Key::Newline,
+                "cr" => Key::CarriageReturn,
+                "tab" => Key::Tab,
+                "bs" | "backspace" => Key::Backspace,
+                "del" | "delete" => Key::Delete,
+                "esc" | "escape" => Key::Escape,
+                "up" => Key::Arrow(DirectionKey::Up),
+                "down" => Key::Arrow(DirectionKey::Down),
+                "left" => Key::Arrow(DirectionKey::Left),
+                "right" => Key::Arrow(DirectionKey::Right),
+                "home" => Key::Home,
+                "end" => Key::End,
+                "ins" | "insert" => Key::Insert,
+                "pageup" | "pgup" => Key::PageUp,
+                "pagedown" | "pgdn" => Key::PageDown,
+                "space" => Key::Character(' '),
+                "lt" => Key::Character('<'),
+                "gt" => Key::Character('>'),
+                f if f.starts_with('f') => {
+                    match f[1..].parse::<u8>() {
+                        Ok(n @ 1..=12) => Key::Function(n),
+                        _ => { return None; }
+                    }
+                },
+                _ => { return None; }
+            }
+        }
+    };
+
+    match key {
+        _ if modifiers.is_empty() => Some(key),
+        Key::Character(c) if modifiers == KeyModifiers::CTRL && c.is_ascii_alphabetic() => {
+            // decode the control byte, `<C-c>` is a Break
+            let byte = c.to_ascii_lowercase() as u8 - b'a' + 0x01;
+            TerminalKeyDecoder::new().decode(byte).ok()
+        },
+        _ => modified_key(key).map(|k| Key::Modified(modifiers, k))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_key_script() {
+        assert_eq!(Ok(vec![Key::Character('c'), Key::Character('d'), Key::Character(' '), Key::Tab,
+                           Key::Break, Key::Eot, Key::Control('w'), Key::Character('<'),
+                           Key::Modified(KeyModifiers::SHIFT, ModifiedKey::Tab),
+                           Key::Modified(KeyModifiers::ALT, ModifiedKey::Character('b')),
+                           Key::Modified(KeyModifiers { shift: true, alt: false, ctrl: true }, ModifiedKey::Arrow(DirectionKey::Up)),
+                           Key::Function(12), Key::Character('\u{e9}'), Key::Newline]),
+                   parse_key_script("cd <Tab><C-c><C-d><C-w><lt><S-Tab><M-b><C-S-Up><F12>\u{e9}\n"));
+
+        assert_eq!(Err(KeyScriptError::UnclosedKey { position: 2 }), parse_key_script("ab<Tab"));
+        assert_eq!(Err(KeyScriptError::UnknownKey { position: 1 }), parse_key_script("a<Foo>"));
+        assert_eq!(Err(KeyScriptError::UnknownKey { position: 0 }), parse_key_script("<F13>"));
+        assert_eq!(Err(KeyScriptError::NotEncodable(Key::Modified(KeyModifiers::CTRL, ModifiedKey::Tab))), key_script_to_bytes("<C-Tab>"));
+    }
+
+    #[test]
+    fn test_key_script_round_trip() {
+        fn round_trip(keys: &[Key]) -> Vec<Key> {
+            let mut decoder = TerminalKeyDecoder::new();
+            let mut decoded: Vec<Key> = encode_keys(keys).unwrap().iter().filter_map(|b| decoder.decode(*b).ok()).collect();
+            decoded.extend(decoder.flush());
+            decoded
+        }
+
+        let script = "ls -l<Tab><Up><Down><Left><Right><Home><End><Ins><Del><PgUp><PgDn>\
+                      <F1><F4><F5><F6><F10><F11><F12><C-Left><A-Right><S-Home><C-Del><S-F5><S-F1>\
+                      <S-Tab><A-f><A-BS><BS><C-a><C-h><C-c><C-d><CR>\u{e9}\u{65e5}<Esc><Enter><Esc><Esc>\
+                      <Esc><Up><Esc><C-a><Esc>\u{e9}<Esc>";
+        let keys = parse_key_script(script).unwrap();
+        assert_eq!(keys, round_trip(&keys));
+
+        // the keys a terminal can't tell apart
+        assert_eq!(vec![Key::CarriageReturn], round_trip(&parse_key_script("<CR><Enter>").unwrap()));
+        assert_eq!(vec![Key::Newline], round_trip(&parse_key_script("<Enter><CR>").unwrap()));
+        assert_eq!(vec![Key::Backspace], round_trip(&[Key::Control('h')]));
+        assert_eq!(Err(KeyScriptError::NotEncodable(Key::Escape)), key_script_to_bytes("<Esc>a"));
+        assert_eq!(Err(KeyScriptError::NotEncodable(Key::Escape)), key_script_to_bytes("<Esc><BS>"));
+        assert_eq!(b"a\x1b[A\x1b[1;5D\x1b[15~\x1bOP\x03\r".to_vec(), key_script_to_bytes("a<Up><C-Left><F5><F1><C-c><CR>").unwrap());
+    }
+}
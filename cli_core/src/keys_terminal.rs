@@ -3,20 +3,269 @@ use keys::*;
 
 /// Terminal key decoder, from raw bytes to decoded key sequences
 pub struct TerminalKeyDecoder {
-    buffer: Vec<u8>
+    buffer: Vec<u8>,
+    options: TerminalKeyDecoderOptions,
+    /// The newline byte that produced the last key, its pair is swallowed
+    last_newline: Option<u8>,
+    /// Decoded keys not returned yet, when a single byte produced two keys
+    pending: VecDeque<Key>
+}
+
+/// Normalization of the input differences between terminal clients
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TerminalKeyDecoderOptions {
+    /// Decode the `\r\n`, `\n\r` and `\r\0` pairs as a single key, the one of their
+    /// first byte: `\r` is a `Key::CarriageReturn`, `\n` a `Key::Newline`. Telnet clients
+    /// send `\r\0` or `\r\n`, serial terminals often just `\r`. Which of the keys ends
+    /// the line is up to `PromptBufferOptions::newline_key_sequence`. When disabled, every
+    /// byte is decoded on its own.
+    pub normalize_newlines: bool,
+    /// Decode 0x08 as `Key::Backspace`, as sent by some PuTTY and serial terminal
+    /// configurations, instead of `Key::Control('h')`
+    pub backspace_ctrl_h: bool
+}
+
+impl Default for TerminalKeyDecoderOptions {
+    fn default() -> TerminalKeyDecoderOptions {
+        TerminalKeyDecoderOptions {
+            normalize_newlines: true,
+            backspace_ctrl_h: true
+        }
+    }
 }
 
 impl TerminalKeyDecoder {
-    /// Create a new decoder
+    /// Create a new decoder with the default options
 	pub fn new() -> TerminalKeyDecoder {
+		TerminalKeyDecoder::with_options(Default::default())
+	}
+
+	/// Create a new decoder
+	pub fn with_options(options: TerminalKeyDecoderOptions) -> TerminalKeyDecoder {
 		TerminalKeyDecoder {
-			buffer: Vec::new()
+			buffer: Vec::new(),
+			options: options,
+			last_newline: None,
+			pending: VecDeque::new()
+		}
+	}
+
+	/// Collapse the newline pairs into the key produced by their first byte. Returns
+	/// `None` if the byte isn't part of a newline.
+	fn decode_newline(&mut self, byte: u8) -> Option<Result<Key, KeyDecoderError>> {
+		let last_newline = self.last_newline.take();
+
+		match (last_newline, byte) {
+			(Some(b'\r'), b'\n') | (Some(b'\r'), 0) | (Some(b'\n'), b'\r') => {
+				// the second half of a pair, no key yet
+				Some(Err(KeyDecoderError::MoreInputRequired))
+			},
+			(_, b'\r') => {
+				self.last_newline = Some(byte);
+				Some(Ok(Key::CarriageReturn))
+			},
+			(_, b'\n') => {
+				self.last_newline = Some(byte);
+				Some(Ok(Key::Newline))
+			},
+			_ => None
 		}
 	}
 }
 
+impl TerminalKeyDecoder {
+    /// Decode the escape sequence collected in the buffer
+    fn decode_escape(&self) -> Result<Key, KeyDecoderError> {
+        match self.buffer.get(1).cloned() {
+            // CSI, ESC [
+            Some(b'[') => {
+                // Linux console function keys, ESC [ [ A to ESC [ [ E
+                if self.buffer.get(2) == Some(&b'[') {
+                    return match self.buffer.get(3).cloned() {
+                        Some(c @ b'A'..=b'E') => Ok(Key::Function(c - b'A' + 1)),
+                        Some(_) => Err(KeyDecoderError::UnknownSequence),
+                        None => Err(KeyDecoderError::MoreInputRequired)
+                    };
+                }
+
+                let body = &self.buffer[2..];
+                match body.last().cloned() {
+                    None => Err(KeyDecoderError::MoreInputRequired),
+                    Some(c @ 0x40..=0x7E) => decode_csi(&body[..body.len() - 1], c),
+                    Some(0x20..=0x3F) if body.len() < MAX_SEQUENCE_LENGTH => Err(KeyDecoderError::MoreInputRequired),
+                    Some(_) => Err(KeyDecoderError::UnknownSequence)
+                }
+            },
+            // SS3, ESC O
+            Some(b'O') => {
+                match self.buffer.get(2).cloned() {
+                    Some(c) => decode_ss3(c),
+                    None => Err(KeyDecoderError::MoreInputRequired)
+                }
+            },
+            // Alt combined with a key is sent with an ESC prefix
+            Some(c @ 0x20..=0x7E) => Ok(Key::Modified(KeyModifiers::ALT, ModifiedKey::Character(c as char))),
+            Some(0x7F) => Ok(Key::Modified(KeyModifiers::ALT, ModifiedKey::Backspace)),
+            Some(_) => Err(KeyDecoderError::UnknownSequence),
+            None => Err(KeyDecoderError::MoreInputRequired)
+        }
+    }
+}
+
+/// Longest parameter list accepted in a CSI sequence before it is discarded
+const MAX_SEQUENCE_LENGTH: usize = 16;
+
+/// Parse the numeric parameters of a CSI sequence, `1;5` into `[1, 5]`. Missing
+/// parameters are returned as zero.
+fn csi_parameters(params: &[u8]) -> Option<Vec<u32>> {
+    if params.is_empty() {
+        return Some(vec![]);
+    }
+
+    params.split(|b| *b == b';').map(|p| {
+        if p.iter().all(|b| b.is_ascii_digit()) {
+            Some(p.iter().fold(0u32, |n, b| n.saturating_mul(10).saturating_add((b - b'0') as u32)))
+        } else {
+            None
+        }
+    }).collect()
+}
+
+/// Decode a complete CSI sequence, given its parameter bytes and the final byte. The
+/// second parameter carries the modifiers, as in `ESC [ 1 ; 5 D` or `ESC [ 3 ; 2 ~`.
+fn decode_csi(params: &[u8], final_byte: u8) -> Result<Key, KeyDecoderError> {
+    let params = csi_parameters(params).ok_or(KeyDecoderError::UnknownSequence)?;
+    let modifiers = KeyModifiers::from_csi_parameter(params.get(1).cloned().unwrap_or(1));
+
+    let key = match final_byte {
+        b'A' => ModifiedKey::Arrow(DirectionKey::Up),
+        b'B' => ModifiedKey::Arrow(DirectionKey::Down),
+        b'C' => ModifiedKey::Arrow(DirectionKey::Right),
+        b'D' => ModifiedKey::Arrow(DirectionKey::Left),
+        b'H' => ModifiedKey::Home,
+        b'F' => ModifiedKey::End,
+        // cursor position report. On the first row it can't be told apart from F3 with
+        // modifiers, which terminals send as `ESC [ 1 ; modifiers R`, so it's decoded as F3.
+        b'R' if params.len() == 2 && params[0] > 1 => {
+            return Ok(Key::CursorPosition { row: to_u16(params[0]), column: to_u16(params[1]) });
+        },
+        // window size report, `ESC [ 8 ; rows ; columns t`
+        b't' if params.len() == 3 && params[0] == 8 => {
+            return Ok(Key::WindowSize { rows: to_u16(params[1]), columns: to_u16(params[2]) });
+        },
+        b'P'..=b'S' => ModifiedKey::Function(final_byte - b'P' + 1),
+        // back tab
+        b'Z' => {
+            return Ok(Key::Modified(KeyModifiers::SHIFT, ModifiedKey::Tab));
+        },
+        // rxvt shifted arrows
+        b'a'..=b'd' => {
+            return Ok(Key::Modified(KeyModifiers::SHIFT, rxvt_arrow(final_byte)));
+        },
+        b'~' => {
+            match params.first().cloned() {
+                Some(1) | Some(7) => ModifiedKey::Home,
+                Some(2) => ModifiedKey::Insert,
+                Some(3) => ModifiedKey::Delete,
+                Some(4) | Some(8) => ModifiedKey::End,
+                Some(5) => ModifiedKey::PageUp,
+                Some(6) => ModifiedKey::PageDown,
+                Some(n @ 11..=15) => ModifiedKey::Function((n - 10) as u8),
+                Some(n @ 17..=21) => ModifiedKey::Function((n - 11) as u8),
+                Some(n @ 23..=24) => ModifiedKey::Function((n - 12) as u8),
+                _ => { return Err(KeyDecoderError::UnknownSequence); }
+            }
+        },
+        _ => { return Err(KeyDecoderError::UnknownSequence); }
+    };
+
+    Ok(Key::modified(modifiers, key))
+}
+
+fn to_u16(n: u32) -> u16 {
+    min(n, u16::max_value() as u32) as u16
+}
+
+/// Decode the byte following ESC O, sent by terminals in application cursor mode
+fn decode_ss3(byte: u8) -> Result<Key, KeyDecoderError> {
+    match byte {
+        b'A' => Ok(Key::Arrow(DirectionKey::Up)),
+        b'B' => Ok(Key::Arrow(DirectionKey::Down)),
+        b'C' => Ok(Key::Arrow(DirectionKey::Right)),
+        b'D' => Ok(Key::Arrow(DirectionKey::Left)),
+        b'H' => Ok(Key::Home),
+        b'F' => Ok(Key::End),
+        b'P'..=b'S' => Ok(Key::Function(byte - b'P' + 1)),
+        // rxvt arrows with Ctrl
+        b'a'..=b'd' => Ok(Key::Modified(KeyModifiers::CTRL, rxvt_arrow(byte))),
+        _ => Err(KeyDecoderError::UnknownSequence)
+    }
+}
+
+fn rxvt_arrow(byte: u8) -> ModifiedKey {
+    match byte {
+        b'a' => ModifiedKey::Arrow(DirectionKey::Up),
+        b'b' => ModifiedKey::Arrow(DirectionKey::Down),
+        b'c' => ModifiedKey::Arrow(DirectionKey::Right),
+        _ => ModifiedKey::Arrow(DirectionKey::Left)
+    }
+}
+
 impl KeyDecoder for TerminalKeyDecoder {
 	fn decode(&mut self, byte: u8) -> Result<Key, KeyDecoderError> {
+		// ESC followed by a byte that can't continue a sequence, like a second ESC, Enter or
+		// a Ctrl key, is a standalone Escape key press and the byte is decoded on its own
+		if self.buffer == [0x1B] && !(0x20..=0x7F).contains(&byte) {
+			self.buffer.clear();
+			self.pending.push_back(Key::Escape);
+		}
+
+		let r = self.decode_key(byte);
+		if let Ok(key) = r {
+			self.pending.push_back(key);
+		}
+
+		match self.pending.pop_front() {
+			Some(key) => Ok(key),
+			None => r
+		}
+	}
+
+	fn is_pending(&self) -> bool {
+		!self.buffer.is_empty() || !self.pending.is_empty()
+	}
+
+	/// Keys that are still queued are returned first. A lone ESC becomes a standalone
+	/// Escape key press, other incomplete sequences are discarded.
+	fn flush(&mut self) -> Option<Key> {
+		if let Some(key) = self.pending.pop_front() {
+			return Some(key);
+		}
+
+		let key = if self.buffer == [0x1B] { Some(Key::Escape) } else { None };
+		self.buffer.clear();
+		key
+	}
+}
+
+impl TerminalKeyDecoder {
+	/// Decode a single byte, without the keys queued by earlier bytes
+	fn decode_key(&mut self, byte: u8) -> Result<Key, KeyDecoderError> {
+		// a byte that isn't a continuation breaks the UTF-8 sequence, the sequence is
+		// discarded and the byte is decoded on its own
+		if let Some(&(0xC0..=0xF7)) = self.buffer.first() {
+			if byte & 0xC0 != 0x80 {
+				self.buffer.clear();
+				return self.decode(byte);
+			}
+		}
+
+		if self.options.normalize_newlines && self.buffer.is_empty() {
+			if let Some(r) = self.decode_newline(byte) {
+				return r;
+			}
+		}
+
 		self.buffer.push(byte);
 
 		let mut more_input_required = false;
@@ -24,28 +273,7 @@ impl KeyDecoder for TerminalKeyDecoder {
 		let r = match self.buffer[0] {
 			// ESC
 			0x1B => {
-                match self.buffer.get(1).cloned() {
-					Some(0x5B) => {
-						match self.buffer.get(2).cloned() {
-							Some(0x41) => {
-                                Ok(Key::Arrow(DirectionKey::Up))
-                            },
-                            Some(0x42) => {
-                                Ok(Key::Arrow(DirectionKey::Down))
-                            },
-                            Some(0x43) => {
-                                Ok(Key::Arrow(DirectionKey::Right))
-                            },
-                            Some(0x44) => {
-                                Ok(Key::Arrow(DirectionKey::Left))
-                            },
-                            Some(_) => Err(KeyDecoderError::UnknownSequence),
-                            None => Err(KeyDecoderError::MoreInputRequired)
-						}
-					},
-					Some(_) => Err(KeyDecoderError::UnknownSequence),
-					None => Err(KeyDecoderError::MoreInputRequired)
-				}
+                self.decode_escape()
 			},
 
             0x7F => {
@@ -66,8 +294,34 @@ impl KeyDecoder for TerminalKeyDecoder {
             4 => {
                 Ok(Key::Eot)
             },
+            0x08 if self.options.backspace_ctrl_h => {
+                Ok(Key::Backspace)
+            },
+            c @ 0x01..=0x1A => {
+                Ok(Key::Control((c - 0x01 + b'a') as char))
+            },
+            // lead byte of a multi-byte UTF-8 sequence
+            c @ 0xC0..=0xF7 => {
+                let len = match c {
+                    0xC0..=0xDF => 2,
+                    0xE0..=0xEF => 3,
+                    _ => 4
+                };
+
+                if self.buffer.len() < len {
+                    Err(KeyDecoderError::MoreInputRequired)
+                } else {
+                    match str::from_utf8(&self.buffer).ok().and_then(|s| s.chars().next()) {
+                        Some(c) => Ok(Key::Character(c)),
+                        None => Err(KeyDecoderError::UnknownSequence)
+                    }
+                }
+            },
+            0x80..=0xBF | 0xF8..=0xFF => {
+                Err(KeyDecoderError::UnknownSequence)
+            },
             c => {
-            	Ok(Key::Character(c))
+            	Ok(Key::Character(c as char))
             }
 		};
 
@@ -92,4 +346,189 @@ fn test_escape_sequences() {
         assert_eq!(Err(KeyDecoderError::MoreInputRequired), decoder.decode(91));
         assert_eq!(Ok(Key::Arrow(DirectionKey::Up)), decoder.decode(65));
     }
-}
\ No newline at end of file
+
+    {
+        let mut decoder = TerminalKeyDecoder::new();
+        for b in b"\x1b[3" {
+            assert_eq!(Err(KeyDecoderError::MoreInputRequired), decoder.decode(*b));
+        }
+        assert_eq!(Ok(Key::Delete), decoder.decode(b'~'));
+    }
+
+    {
+        let mut decoder = TerminalKeyDecoder::new();
+        for b in b"\x1b[12;3" {
+            assert_eq!(Err(KeyDecoderError::MoreInputRequired), decoder.decode(*b));
+        }
+        assert_eq!(Err(KeyDecoderError::UnknownSequence), decoder.decode(b'x'));
+        assert_eq!(Ok(Key::Character('a')), decoder.decode(b'a'));
+    }
+
+    {
+        let mut decoder = TerminalKeyDecoder::new();
+        assert_eq!(None, decoder.flush());
+        assert_eq!(Err(KeyDecoderError::MoreInputRequired), decoder.decode(0x1B));
+        assert!(decoder.is_pending());
+        assert_eq!(Some(Key::Escape), decoder.flush());
+        assert!(!decoder.is_pending());
+
+        assert_eq!(Err(KeyDecoderError::MoreInputRequired), decoder.decode(0x1B));
+        assert_eq!(Ok(Key::Escape), decoder.decode(0x1B));
+        assert_eq!(Err(KeyDecoderError::MoreInputRequired), decoder.decode(b'['));
+        assert_eq!(Ok(Key::Arrow(DirectionKey::Up)), decoder.decode(b'A'));
+
+        assert_eq!(Err(KeyDecoderError::MoreInputRequired), decoder.decode(0x1B));
+        assert_eq!(Err(KeyDecoderError::MoreInputRequired), decoder.decode(b'['));
+        assert_eq!(None, decoder.flush());
+        assert_eq!(Ok(Key::Character('A')), decoder.decode(b'A'));
+    }
+
+    {
+        // ESC followed by a byte that doesn't continue a sequence
+        let decode_all = |bytes: &[u8]| {
+            let mut decoder = TerminalKeyDecoder::new();
+            let mut keys: Vec<_> = bytes.iter().filter_map(|b| decoder.decode(*b).ok()).collect();
+            while let Some(key) = decoder.flush() {
+                keys.push(key);
+            }
+            keys
+        };
+
+        assert_eq!(vec![Key::Escape, Key::CarriageReturn], decode_all(b"\x1b\r"));
+        assert_eq!(vec![Key::Escape, Key::CarriageReturn, Key::Character('a')], decode_all(b"\x1b\r\na"));
+        assert_eq!(vec![Key::Escape, Key::Control('a'), Key::Character('b')], decode_all(b"\x1b\x01b"));
+        assert_eq!(vec![Key::Escape, Key::Character('\u{e9}')], decode_all(b"\x1b\xc3\xa9"));
+        assert_eq!(vec![Key::Escape, Key::Escape, Key::Arrow(DirectionKey::Up)], decode_all(b"\x1b\x1b\x1b[A"));
+        assert_eq!(vec![Key::Escape, Key::Tab, Key::Escape], decode_all(b"\x1b\t\x1b"));
+    }
+
+    {
+        let mut decoder = TerminalKeyDecoder::new();
+        assert_eq!(Ok(Key::Control('a')), decoder.decode(0x01));
+        assert_eq!(Ok(Key::Control('w')), decoder.decode(0x17));
+        assert_eq!(Ok(Key::Break), decoder.decode(0x03));
+        assert_eq!(Ok(Key::Tab), decoder.decode(0x09));
+    }
+
+    {
+        let mut decoder = TerminalKeyDecoder::new();
+        assert_eq!(Ok(Key::Character('a')), decoder.decode(b'a'));
+        assert_eq!(Err(KeyDecoderError::MoreInputRequired), decoder.decode(0xC3));
+        assert_eq!(Ok(Key::Character('\u{e9}')), decoder.decode(0xA9));
+        assert_eq!(Err(KeyDecoderError::MoreInputRequired), decoder.decode(0xE6));
+        assert_eq!(Err(KeyDecoderError::MoreInputRequired), decoder.decode(0x97));
+        assert_eq!(Ok(Key::Character('\u{65e5}')), decoder.decode(0xA5));
+        assert_eq!(Err(KeyDecoderError::MoreInputRequired), decoder.decode(0xC3));
+        assert_eq!(Ok(Key::Character('a')), decoder.decode(b'a'));
+        assert_eq!(Err(KeyDecoderError::UnknownSequence), decoder.decode(0xA9));
+
+        // the breaking byte can start a sequence of its own
+        assert_eq!(Err(KeyDecoderError::MoreInputRequired), decoder.decode(0xE6));
+        assert_eq!(Err(KeyDecoderError::MoreInputRequired), decoder.decode(0xC3));
+        assert_eq!(Ok(Key::Character('\u{e9}')), decoder.decode(0xA9));
+        assert_eq!(vec![Key::Character('x'), Key::CarriageReturn],
+                   b"\xc3x\xe6\x97\r".iter().filter_map(|b| decoder.decode(*b).ok()).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn test_normalization() {
+    let decode_all = |decoder: &mut TerminalKeyDecoder, bytes: &[u8]| {
+        bytes.iter().filter_map(|b| decoder.decode(*b).ok()).collect::<Vec<_>>()
+    };
+
+    let mut decoder = TerminalKeyDecoder::new();
+    assert_eq!(vec![Key::Character('a'), Key::CarriageReturn, Key::Character('b'), Key::CarriageReturn],
+               decode_all(&mut decoder, b"a\r\nb\r\0"));
+    assert_eq!(vec![Key::CarriageReturn, Key::CarriageReturn], decode_all(&mut decoder, b"\r\r\n"));
+    assert_eq!(vec![Key::Newline, Key::Newline], decode_all(&mut decoder, b"\n\r\n"));
+    assert_eq!(vec![Key::Character('x'), Key::Backspace, Key::Backspace], decode_all(&mut decoder, b"x\x08\x7f"));
+
+    let mut decoder = TerminalKeyDecoder::with_options(TerminalKeyDecoderOptions {
+        normalize_newlines: false,
+        backspace_ctrl_h: false
+    });
+    assert_eq!(vec![Key::CarriageReturn, Key::Newline, Key::Control('h')], decode_all(&mut decoder, b"\r\n\x08"));
+}
+
+#[test]
+fn test_terminal_emulator_sequences() {
+    let sequences: &[(&[u8], Key)] = &[
+        // xterm, VTE, iTerm2
+        (b"\x1b[A", Key::Arrow(DirectionKey::Up)),
+        (b"\x1b[B", Key::Arrow(DirectionKey::Down)),
+        (b"\x1b[C", Key::Arrow(DirectionKey::Right)),
+        (b"\x1b[D", Key::Arrow(DirectionKey::Left)),
+        (b"\x1b[H", Key::Home),
+        (b"\x1b[F", Key::End),
+        (b"\x1b[2~", Key::Insert),
+        (b"\x1b[3~", Key::Delete),
+        (b"\x1b[5~", Key::PageUp),
+        (b"\x1b[6~", Key::PageDown),
+        (b"\x1bOP", Key::Function(1)),
+        (b"\x1bOQ", Key::Function(2)),
+        (b"\x1bOR", Key::Function(3)),
+        (b"\x1bOS", Key::Function(4)),
+        (b"\x1b[15~", Key::Function(5)),
+        (b"\x1b[17~", Key::Function(6)),
+        (b"\x1b[18~", Key::Function(7)),
+        (b"\x1b[19~", Key::Function(8)),
+        (b"\x1b[20~", Key::Function(9)),
+        (b"\x1b[21~", Key::Function(10)),
+        (b"\x1b[23~", Key::Function(11)),
+        (b"\x1b[24~", Key::Function(12)),
+        // application cursor mode
+        (b"\x1bOA", Key::Arrow(DirectionKey::Up)),
+        (b"\x1bOB", Key::Arrow(DirectionKey::Down)),
+        (b"\x1bOC", Key::Arrow(DirectionKey::Right)),
+        (b"\x1bOD", Key::Arrow(DirectionKey::Left)),
+        (b"\x1bOH", Key::Home),
+        (b"\x1bOF", Key::End),
+        // VT220, PuTTY, tmux, screen
+        (b"\x1b[1~", Key::Home),
+        (b"\x1b[4~", Key::End),
+        (b"\x1b[11~", Key::Function(1)),
+        (b"\x1b[12~", Key::Function(2)),
+        (b"\x1b[13~", Key::Function(3)),
+        (b"\x1b[14~", Key::Function(4)),
+        // rxvt
+        (b"\x1b[7~", Key::Home),
+        (b"\x1b[8~", Key::End),
+        // Linux console
+        (b"\x1b[[A", Key::Function(1)),
+        (b"\x1b[[B", Key::Function(2)),
+        (b"\x1b[[C", Key::Function(3)),
+        (b"\x1b[[D", Key::Function(4)),
+        (b"\x1b[[E", Key::Function(5)),
+        // modifiers
+        (b"\x1b[1;5D", Key::Modified(KeyModifiers::CTRL, ModifiedKey::Arrow(DirectionKey::Left))),
+        (b"\x1b[1;5C", Key::Modified(KeyModifiers::CTRL, ModifiedKey::Arrow(DirectionKey::Right))),
+        (b"\x1b[1;3D", Key::Modified(KeyModifiers::ALT, ModifiedKey::Arrow(DirectionKey::Left))),
+        (b"\x1b[1;2H", Key::Modified(KeyModifiers::SHIFT, ModifiedKey::Home)),
+        (b"\x1b[1;6A", Key::Modified(KeyModifiers { shift: true, alt: false, ctrl: true }, ModifiedKey::Arrow(DirectionKey::Up))),
+        (b"\x1b[1;9B", Key::Modified(KeyModifiers::ALT, ModifiedKey::Arrow(DirectionKey::Down))),
+        (b"\x1b[3;5~", Key::Modified(KeyModifiers::CTRL, ModifiedKey::Delete)),
+        (b"\x1b[15;2~", Key::Modified(KeyModifiers::SHIFT, ModifiedKey::Function(5))),
+        (b"\x1b[1;2P", Key::Modified(KeyModifiers::SHIFT, ModifiedKey::Function(1))),
+        (b"\x1b[1;1C", Key::Arrow(DirectionKey::Right)),
+        (b"\x1b[Z", Key::Modified(KeyModifiers::SHIFT, ModifiedKey::Tab)),
+        (b"\x1b[d", Key::Modified(KeyModifiers::SHIFT, ModifiedKey::Arrow(DirectionKey::Left))),
+        (b"\x1bOc", Key::Modified(KeyModifiers::CTRL, ModifiedKey::Arrow(DirectionKey::Right))),
+        (b"\x1b[24;80R", Key::CursorPosition { row: 24, column: 80 }),
+        (b"\x1b[1;2R", Key::Modified(KeyModifiers::SHIFT, ModifiedKey::Function(3))),
+        (b"\x1b[1;80R", Key::Modified(KeyModifiers { shift: true, alt: true, ctrl: true }, ModifiedKey::Function(3))),
+        (b"\x1b[8;24;80t", Key::WindowSize { rows: 24, columns: 80 }),
+        (b"\x1bb", Key::Modified(KeyModifiers::ALT, ModifiedKey::Character('b'))),
+        (b"\x1bF", Key::Modified(KeyModifiers::ALT, ModifiedKey::Character('F'))),
+        (b"\x1b\x7f", Key::Modified(KeyModifiers::ALT, ModifiedKey::Backspace))
+    ];
+
+    let mut decoder = TerminalKeyDecoder::new();
+    for &(bytes, key) in sequences {
+        let (last, init) = bytes.split_last().unwrap();
+        for b in init {
+            assert_eq!(Err(KeyDecoderError::MoreInputRequired), decoder.decode(*b), "{:?}", bytes);
+        }
+        assert_eq!(Ok(key), decoder.decode(*last), "{:?}", bytes);
+    }
+}
@@ -23,8 +23,7 @@
 //! let options = PromptBufferOptions { echo: true, ..Default::default() };
 //! let mut prompt = PromptBuffer::new(options);
 //!
-//! let input_keys = [Key::Character('h' as u8), Key::Character('e' as u8), Key::Character('l' as u8),
-//!                   Key::Character('p' as u8), Key::Newline];
+//! let input_keys = parse_key_script("help<Enter>").unwrap();
 //! 
 //! for key in &input_keys {
 //!     let p = prompt.handle_key(*key, &mut terminal, |mut m| {
@@ -59,24 +58,39 @@ mod prelude;
 mod cli;
 mod cli_command;
 mod cli_property;
+mod cli_execute;
+mod cli_args;
+mod tokenizer;
 mod keys;
 mod keys_terminal;
+mod keys_script;
 mod terminal;
 mod prompt_buffer;
+mod history;
+#[cfg(feature="std")]
+mod console_server;
 
 
 pub mod i18n;
+pub mod telnet;
 
 pub use autocomplete::*;
 pub use utils::*;
 pub use cli::*;
 pub use cli_command::*;
 pub use cli_property::*;
+pub use cli_execute::*;
+pub use cli_args::*;
+pub use tokenizer::*;
 pub use keys::*;
 pub use keys_terminal::*;
+pub use keys_script::*;
 pub use property::*;
 pub use terminal::*;
 pub use prompt_buffer::*;
+pub use history::*;
+#[cfg(feature="std")]
+pub use console_server::*;
 
 #[cfg(test)]
 mod tests;
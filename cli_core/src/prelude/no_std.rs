@@ -1,7 +1,8 @@
 
 pub use core::marker::PhantomData;
+pub use core::any::Any;
 pub use core::iter;
-pub use core::cell::RefCell;
+pub use core::cell::{Cell, RefCell};
 pub use core::fmt;
 pub use core::fmt::Debug;
 pub use core::fmt::Write as FmtWrite;
@@ -18,6 +19,7 @@ pub use alloc::arc::Arc;
 pub use alloc::boxed::Box;
 
 pub use collections::vec::Vec;
+pub use collections::vec_deque::VecDeque;
 pub use collections::string::*;
 pub use collections::str::FromStr;
 pub use collections::str;
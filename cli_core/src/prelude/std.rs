@@ -1,5 +1,5 @@
 pub use std::prelude::v1::*;
-pub use std::cell::RefCell;
+pub use std::cell::{Cell, RefCell};
 pub use std::rc::Rc;
 pub use std::num::Wrapping;
 pub use std::fmt;
@@ -10,6 +10,7 @@ pub use std::fmt::Write as FmtWrite;
 pub use std::fmt::Error as FmtError;
 pub use std::mem;
 pub use std::marker::PhantomData;
+pub use std::any::Any;
 pub use std::ops::Range;
 pub use std::cmp::{min, max};
 pub use std::ptr::write_bytes;
@@ -21,3 +22,4 @@ pub use std::io::Write;
 pub use std::str;
 pub use std::ops::Index;
 pub use std::ops::{Deref, DerefMut};
+pub use std::collections::VecDeque;
@@ -1,9 +1,12 @@
 use prelude::v1::*;
 use autocomplete::*;
 use cli::*;
+use cli_command::*;
 use keys::*;
 use terminal::*;
 use utils::*;
+use history::*;
+use tokenizer::*;
 
 use i18n::*;
 
@@ -15,16 +18,51 @@ pub enum PromptEvent {
 
 enum AutocompleteRequest {	
 	None,
-	HaveMultipleOptions { lines: Vec<AutocompleteLine> }
+	/// Candidates of the last completion, `selected` is the one inserted into the line
+	/// while cycling through them with Shift+Tab
+	HaveMultipleOptions { lines: Vec<AutocompleteLine>, selected: Option<usize> }
+}
+
+/// State of the reverse incremental history search
+struct HistorySearch {
+	query: String,
+	/// Index and contents of the currently matched history line
+	found: Option<(usize, String)>,
+	/// Did the last search attempt fail?
+	failed: bool,
+	original_line: Vec<char>,
+	original_cursor: usize
+}
+
+/// What to do with a finished line
+enum LineAction {
+	None,
+	Execute { line: String },
+	Answer { asked: AskedQuestion, answer: Answer },
+	ChangePath { path: String },
+	Help { arg: String }
 }
 
 /// Holds the current line buffer for a terminal and its possible autocomplete state.
 pub struct PromptBuffer {
-	line_buffer: Vec<u8>,
+	line_buffer: Vec<char>,
+	/// Position of the cursor in the line buffer, in characters
+	cursor: usize,
 	change_path_enabled: bool,
 	current_path: Vec<String>,
 	path_separator: char,
 	autocomplete: AutocompleteRequest,
+	history: LineHistory,
+	history_store: Option<Box<HistoryStore>>,
+	search: Option<HistorySearch>,
+	/// The question of a command, waiting for the user to answer it
+	pending_question: Option<AskedQuestion>,
+	/// Columns and rows of the terminal
+	terminal_size: (u16, u16),
+	/// Was the terminal asked for its size, without a reply yet
+	size_query_pending: bool,
+	/// Display width of the last printed prompt
+	prompt_width: Cell<usize>,
 	options: PromptBufferOptions,
 	strings: Box<Strings>
 }
@@ -36,19 +74,49 @@ pub enum NewlineSequence {
 	NewlineOrCarriageReturn
 }
 
+/// Feedback to the user when typed input is rejected, for example when the line
+/// would exceed the maximum length.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum InputRejectedFeedback {
+	/// Silently ignore the input
+	None,
+	/// Ring the terminal's bell
+	Bell,
+	/// Print a message from `Strings` and redraw the line
+	Message
+}
+
 /// Options for the prompt buffer
+#[derive(Clone)]
 pub struct PromptBufferOptions {
 	/// Prompt sequence to be printed after every newline
 	pub prompt: Cow<'static, str>,
 	/// Newline sequence to be used while writing
 	pub newline: Cow<'static, str>,
-	/// Maximum size of the line buffer
+	/// Maximum size of the line buffer, in characters
 	pub max_line_length: usize,
+	/// Feedback when a key press or an autocomplete would make the line too long
+	pub input_rejected_feedback: InputRejectedFeedback,
+	/// Enables the built-in `cd` command for navigating through the command tree. Commands
+	/// are then resolved relative to the current path, unless they start with the separator.
+	pub change_path_enabled: bool,
+	/// Enables the built-in `help` command. It lists the commands and properties starting
+	/// with its argument, along with the first line of their help. `help <command>` shows
+	/// the entire help of the command.
+	pub help_enabled: bool,
+	/// Typing `?` lists the possible continuations of the line in front of the cursor, along
+	/// with their help, instead of inserting the character.
+	pub contextual_help: bool,
 	/// Echo the typed characters?
 	pub echo: bool,
 	/// Input newline key sequence
-	pub newline_key_sequence: NewlineSequence
-
+	pub newline_key_sequence: NewlineSequence,
+	/// Number of executed lines to remember for the Up and Down keys. Zero disables the history.
+	pub history_size: usize,
+	/// Don't record a line identical to the previous one in the history
+	pub history_ignore_duplicates: bool,
+	/// Don't record lines starting with a space in the history
+	pub history_ignore_space: bool
 }
 
 impl Default for PromptBufferOptions {
@@ -58,7 +126,14 @@ impl Default for PromptBufferOptions {
 			echo: true,
 			newline: "\r\n".into(),
 			max_line_length: 512,
-			newline_key_sequence: NewlineSequence::NewlineOrCarriageReturn
+			input_rejected_feedback: InputRejectedFeedback::Bell,
+			change_path_enabled: false,
+			help_enabled: false,
+			contextual_help: false,
+			newline_key_sequence: NewlineSequence::NewlineOrCarriageReturn,
+			history_size: 20,
+			history_ignore_duplicates: true,
+			history_ignore_space: false
 		}
 	}
 }
@@ -66,19 +141,47 @@ impl Default for PromptBufferOptions {
 impl PromptBuffer {
 	/// Create a new prompt buffer
 	pub fn new(options: PromptBufferOptions) -> PromptBuffer {
-		PromptBuffer {
+		let mut history = LineHistory::new(options.history_size);
+		history.set_ignore_duplicates(options.history_ignore_duplicates);
+		history.set_ignore_space(options.history_ignore_space);
+
+		let prompt = PromptBuffer {
 			line_buffer: Vec::new(),
-			change_path_enabled: false,
+			cursor: 0,
+			change_path_enabled: options.change_path_enabled,
 			current_path: vec![],
 			path_separator: '/',
 			autocomplete: AutocompleteRequest::None,
+			history: history,
+			history_store: None,
+			search: None,
+			pending_question: None,
+			terminal_size: (80, 24),
+			size_query_pending: false,
+			prompt_width: Cell::new(0),
 			options: options,
 			strings: Box::new(English)
-		}
+		};
+
+		// measure the prompt, the line can be edited before the prompt was printed
+		prompt.print_prompt(&mut StringWriter(String::new()));
+		prompt
 	}
 	
 	/// Print the prompt
 	pub fn print_prompt<T: CharacterTerminalWriter>(&self, output: &mut T) {
+		let mut prompt = StringWriter(String::new());
+		self.render_prompt(&mut prompt);
+		self.prompt_width.set(printed_str_width(&prompt.0));
+		output.print_str(&prompt.0);
+	}
+
+	fn render_prompt<T: CharacterTerminalWriter>(&self, output: &mut T) {
+		if let Some(ref pending) = self.pending_question {
+			self.strings.question_prompt(output, pending.get_question()).ok();
+			return;
+		}
+
 		if !self.options.prompt.len() == 0 { return; }
 
 		if self.change_path_enabled {
@@ -100,6 +203,533 @@ impl PromptBuffer {
 		}
 	}
 
+	/// The path the user navigated to with `cd`, joined with the path separator. Empty at the root.
+	pub fn get_current_path(&self) -> String {
+		self.current_path.join(&self.path_separator.to_string())
+	}
+
+	/// Resolve the line against the current path. Returns the line for the matcher, the
+	/// prefix that was added for the matcher and the prefix that was removed from the line.
+	fn resolve_line(&self, line: &str) -> (String, String, String) {
+		if !self.change_path_enabled {
+			return (line.into(), "".into(), "".into());
+		}
+
+		if line.starts_with(self.path_separator) {
+			let sep = self.path_separator.to_string();
+			return (line[sep.len()..].into(), "".into(), sep);
+		}
+
+		let prefix: String = self.current_path.iter().map(|p| format!("{}{}", p, self.path_separator)).collect();
+		(format!("{}{}", prefix, line), prefix, "".into())
+	}
+
+	/// Built-in `cd` command. The new path is accepted if there are any commands under it.
+	fn change_path<T, F: FnOnce(&mut CliExecutor)>(&mut self, arg: &str, terminal: &mut T, call_commands: F)
+		where T: CharacterTerminalWriter + FmtWrite
+	{
+		let sep = self.path_separator;
+
+		let mut path = if arg.starts_with(sep) { vec![] } else { self.current_path.clone() };
+		for part in arg.split(sep) {
+			match part.trim() {
+				"" | "." => (),
+				".." => { path.pop(); },
+				part => path.push(part.to_string())
+			}
+		}
+
+		let exists = path.is_empty() || {
+			let prefix: String = path.iter().map(|p| format!("{}{}", p, sep)).collect();
+			let matcher = CliLineMatcher::new(&prefix, LineMatcherMode::AutocompleteOnly);
+			let mut executor = CliExecutor::new(matcher, &*self.strings, terminal);
+			call_commands(&mut executor);
+
+			match executor.close().finish() {
+				LineBufferResult::Autocomplete { result: AutocompleteResult::None } => false,
+				LineBufferResult::Autocomplete { .. } => true,
+				_ => false
+			}
+		};
+
+		if exists {
+			self.current_path = path;
+		} else {
+			self.strings.path_not_found(terminal, arg).ok();
+			terminal.newline();
+		}
+	}
+
+	/// Built-in `help` command. Collects the commands with the introspection mode of the matcher.
+	fn print_help<T, F: FnOnce(&mut CliExecutor)>(&mut self, arg: &str, terminal: &mut T, call_commands: F)
+		where T: CharacterTerminalWriter + FmtWrite
+	{
+		let (filter, added, removed) = self.resolve_line(arg);
+
+		let commands = {
+			let matcher = CliLineMatcher::new(&filter, LineMatcherMode::Introspect);
+			let mut executor = CliExecutor::new(matcher, &*self.strings, terminal);
+			call_commands(&mut executor);
+
+			match executor.close().finish() {
+				LineBufferResult::Introspection { commands } => commands,
+				_ => vec![]
+			}
+		};
+
+		// relative to the current path, as the user would type them
+		let names: Vec<String> = commands.iter()
+			.map(|c| format!("{}{}", removed, c.command.get(added.len()..).unwrap_or("").trim()))
+			.collect();
+
+		if !arg.is_empty() {
+			if let Some(i) = commands.iter().position(|c| c.command.trim() == filter.trim()) {
+				terminal.print_line(&names[i]);
+				if let Some(ref help) = commands[i].help {
+					for line in help.lines() {
+						terminal.print_line(&format!("  {}", line));
+					}
+				}
+				return;
+			}
+		}
+
+		if commands.is_empty() {
+			self.strings.cmd_not_recognized(terminal, arg).ok();
+			terminal.newline();
+			return;
+		}
+
+		let width = names.iter().map(|n| str_display_width(n)).max().unwrap_or(0);
+		for (name, cmd) in names.iter().zip(&commands) {
+			match cmd.help.as_ref().and_then(|h| h.lines().next()) {
+				Some(summary) => {
+					let padding = width - str_display_width(name) + 2;
+					terminal.print_line(&format!("{}{}{}", name, " ".repeat(padding), summary));
+				},
+				None => terminal.print_line(name)
+			}
+		}
+	}
+
+	/// Contextual help for the `?` key. Lists the autocomplete suggestions for the line in front
+	/// of the cursor with the first line of their help, then redraws the line. After a complete
+	/// command, also shows the usage of its arguments and, once the required ones were typed,
+	/// `<cr>` for executing it as it is.
+	fn contextual_help<T, F: FnOnce(&mut CliExecutor)>(&mut self, terminal: &mut T, call_commands: F)
+		where T: CharacterTerminalWriter + FmtWrite
+	{
+		let line: String = self.line_buffer[..self.cursor].iter().collect();
+		let (resolved_line, matcher_prefix, line_prefix) = self.resolve_line(&line);
+
+		let (candidates, entered) = {
+			let matcher = CliLineMatcher::new(&resolved_line, LineMatcherMode::AutocompleteOnly);
+			let mut executor = CliExecutor::new(matcher, &*self.strings, terminal);
+			call_commands(&mut executor);
+			let matcher = executor.close();
+			let entered = matcher.get_entered_command().cloned();
+			let candidates: Vec<AutocompleteLine> = matcher.finish_candidates().iter().map(|l| l.replace_prefix(&matcher_prefix, &line_prefix)).collect();
+			(candidates, entered)
+		};
+
+		// continue below the entire line
+		if self.options.echo {
+			self.move_cursor(terminal, self.column(self.cursor), self.column(self.line_buffer.len()));
+		}
+		terminal.print_line("");
+
+		if let Some(usage) = entered.as_ref().and_then(|e| e.usage.as_ref()) {
+			let cmd = line.split_whitespace().next().unwrap_or("");
+			self.strings.command_usage(terminal, cmd, usage).ok();
+			terminal.newline();
+		}
+
+		let mut entries: Vec<(&str, Option<&str>)> = candidates.iter().map(|l| (l.get_display(), l.get_help())).collect();
+		if let Some(ref entered) = entered {
+			if entered.executable {
+				entries.push(("<cr>", entered.help.as_ref().map(|h| h.as_str())));
+			}
+		}
+
+		if entries.is_empty() {
+			self.strings.cmd_not_recognized(terminal, line.trim()).ok();
+			terminal.newline();
+		} else {
+			let width = entries.iter().map(|e| str_display_width(e.0)).max().unwrap_or(0);
+			let entries: Vec<String> = entries.iter().map(|&(display, help)| {
+				match help.and_then(|h| h.lines().next()) {
+					Some(summary) => {
+						let padding = width - str_display_width(display) + 2;
+						format!("{}{}{}", display, " ".repeat(padding), summary)
+					},
+					None => display.into()
+				}
+			}).collect();
+
+			let entries: Vec<&str> = entries.iter().map(|e| e.as_str()).collect();
+			format_in_columns(&entries, self.terminal_size.0, 4, &self.options.newline, terminal).ok();
+		}
+
+		self.redraw_line(terminal, 0);
+	}
+
+	/// The history of the executed lines
+	pub fn get_history(&mut self) -> &mut LineHistory {
+		&mut self.history
+	}
+
+	/// Load the history from the store and record all the future lines into it.
+	pub fn set_history_store(&mut self, mut store: Box<HistoryStore>) -> Result<(), HistoryStoreError> {
+		for line in store.load()? {
+			self.history.push(&line);
+		}
+
+		self.history_store = Some(store);
+		Ok(())
+	}
+
+	/// Is the cursor inside a quoted argument? The `?` is typed literally there.
+	fn in_open_quote(&self) -> bool {
+		let line: String = self.line_buffer[..self.cursor].iter().collect();
+		partial_argument(&line).open_quote.is_some()
+	}
+
+	/// Is a command waiting for an answer to its question?
+	fn reading_input(&self) -> bool {
+		self.pending_question.is_some()
+	}
+
+	/// Wait for the answer to the command's question
+	fn ask<T: CharacterTerminalWriter>(&mut self, question: AskedQuestion, terminal: &mut T) {
+		self.strings.question_intro(terminal, question.get_question()).ok();
+		self.pending_question = Some(question);
+	}
+
+	/// Parse the line buffer as the answer to the question
+	fn parse_answer(&self, question: &Question) -> Option<Answer> {
+		let line = self.line();
+		let answer = line.trim();
+
+		match *question {
+			Question::Input(_) => Some(Answer::Input(line.clone())),
+			Question::Confirm { default, .. } => {
+				if answer.is_empty() {
+					Some(Answer::Confirm(default))
+				} else {
+					self.strings.parse_confirmation(answer).map(Answer::Confirm)
+				}
+			},
+			Question::Choice { ref choices, .. } => {
+				match usize::from_str(answer) {
+					Ok(n) if n >= 1 && n <= choices.len() => Some(Answer::Choice(n - 1)),
+					Ok(_) => None,
+					Err(_) => {
+						choices.iter()
+							.position(|c| c.to_lowercase() == answer.to_lowercase())
+							.map(Answer::Choice)
+					}
+				}
+			}
+		}
+	}
+
+	/// Print the characters of the line buffer, masked if a command is reading a secret.
+	fn echo_chars<T: CharacterTerminalWriter>(&self, terminal: &mut T, chars: &[char]) {
+		match self.pending_question.as_ref().map(|p| p.get_question().get_echo()) {
+			Some(InputEcho::Masked(mask)) => {
+				for _ in chars {
+					print_chars(terminal, &[mask]);
+				}
+			},
+			Some(InputEcho::Hidden) => (),
+			Some(InputEcho::Visible) | None => {
+				print_chars(terminal, chars);
+			}
+		}
+	}
+
+	/// Display width of the characters as printed by `echo_chars`
+	fn echo_width(&self, chars: &[char]) -> usize {
+		match self.pending_question.as_ref().map(|p| p.get_question().get_echo()) {
+			Some(InputEcho::Masked(mask)) => chars.len() * char_display_width(mask),
+			Some(InputEcho::Hidden) => 0,
+			Some(InputEcho::Visible) | None => chars_display_width(chars)
+		}
+	}
+
+	/// Remove the character under the cursor, returns its width on the terminal
+	fn remove_at_cursor(&mut self) -> usize {
+		let c = self.line_buffer.remove(self.cursor);
+		self.echo_width(&[c])
+	}
+
+	/// The current contents of the line buffer
+	fn line(&self) -> String {
+		self.line_buffer.iter().collect()
+	}
+
+	/// Replace the contents of the line buffer and redraw the line on the terminal. `from`
+	/// is the column of the terminal's cursor, as in `redraw_line`.
+	fn replace_line<T: CharacterTerminalWriter>(&mut self, line: &str, terminal: &mut T, from: usize) {
+		self.line_buffer.clear();
+		self.line_buffer.extend(line.chars());
+		self.cursor = self.line_buffer.len();
+
+		if self.options.echo {
+			self.redraw_line(terminal, from);
+		}
+	}
+
+	/// Print the prompt and the entire line buffer over the current terminal line,
+	/// then place the terminal's cursor at the buffer's cursor. `from` is the column
+	/// of the terminal's cursor before the redraw, needed to find the first row of a
+	/// line wrapped over multiple rows.
+	fn redraw_line<T: CharacterTerminalWriter>(&self, terminal: &mut T, from: usize) {
+		self.move_cursor(terminal, from, from % self.terminal_columns());
+		terminal.print_str("\r");
+		self.print_prompt(terminal);
+		let end = self.print_at(terminal, &self.line_buffer, self.prompt_width());
+		// erase the rest of the previous line
+		terminal.print_str("\x1b[J");
+		self.move_cursor(terminal, end, self.column(self.cursor));
+	}
+
+	/// Print the part of the line after the cursor, followed by `erase` blanks that
+	/// clear the leftovers of a shortened line, and move the terminal's cursor back.
+	fn redraw_tail<T: CharacterTerminalWriter>(&self, terminal: &mut T, erase: usize) {
+		let from = self.column(self.cursor);
+		let mut end = self.print_at(terminal, &self.line_buffer[self.cursor..], from);
+		if erase > 0 {
+			for _ in 0..erase {
+				terminal.print(&[0x20]);
+			}
+			end = self.wrap_cursor(terminal, end + erase);
+		}
+		self.move_cursor(terminal, end, from);
+	}
+
+	/// Display width of the prompt, without the ANSI escape sequences
+	fn prompt_width(&self) -> usize {
+		self.prompt_width.get()
+	}
+
+	/// Terminal column of the character at `index` of the line buffer. Columns are counted
+	/// from the start of the prompt and continue over the wrapped rows.
+	fn column(&self, index: usize) -> usize {
+		self.prompt_width() + self.echo_width(&self.line_buffer[..index])
+	}
+
+	/// Echo the characters, starting at the column `from`. Returns the column after them.
+	fn print_at<T: CharacterTerminalWriter>(&self, terminal: &mut T, chars: &[char], from: usize) -> usize {
+		self.echo_chars(terminal, chars);
+		let end = from + self.echo_width(chars);
+		if end == from { end } else { self.wrap_cursor(terminal, end) }
+	}
+
+	/// Terminals keep the cursor in the last column after printing up to the right
+	/// edge, and wrap only with the next character. Move it to the next row when it
+	/// ends up at the edge, so the cursor movements can rely on its position.
+	fn wrap_cursor<T: CharacterTerminalWriter>(&self, terminal: &mut T, column: usize) -> usize {
+		if column % self.terminal_columns() == 0 {
+			terminal.print_str("\r\n");
+		}
+		column
+	}
+
+	/// Move the terminal's cursor between two columns, across the wrapped rows.
+	fn move_cursor<T: CharacterTerminalWriter>(&self, terminal: &mut T, from: usize, to: usize) {
+		let width = self.terminal_columns();
+		let (from_row, from_column) = (from / width, from % width);
+		let (to_row, to_column) = (to / width, to % width);
+
+		if to_row < from_row {
+			terminal.print_str(&format!("\x1b[{}A", from_row - to_row));
+		} else if to_row > from_row {
+			terminal.print_str(&format!("\x1b[{}B", to_row - from_row));
+		}
+
+		if to_column < from_column {
+			cursor_left(terminal, from_column - to_column);
+		} else if to_column > from_column {
+			terminal.print_str(&format!("\x1b[{}C", to_column - from_column));
+		}
+	}
+
+	fn terminal_columns(&self) -> usize {
+		max(self.terminal_size.0 as usize, 1)
+	}
+
+	/// Set the size of the terminal, used for laying out the autocomplete suggestions and
+	/// for redrawing the lines that wrap over multiple rows. Defaults to 80 by 24 characters.
+	pub fn set_terminal_size(&mut self, columns: u16, rows: u16) {
+		self.terminal_size = (columns, rows);
+	}
+
+	/// The size of the terminal, in columns and rows
+	pub fn get_terminal_size(&self) -> (u16, u16) {
+		self.terminal_size
+	}
+
+	/// Ask the terminal for its size. The cursor is moved to the bottom right corner and
+	/// its position is queried, then restored. Only the `Key::CursorPosition` that replies
+	/// to the query is used as the size.
+	pub fn query_terminal_size<T: CharacterTerminalWriter>(&mut self, terminal: &mut T) {
+		terminal.print_str("\x1b7\x1b[999;999H\x1b[6n\x1b8");
+		self.size_query_pending = true;
+	}
+
+	/// Turn the echo of the typed characters on or off, for example when a telnet client
+	/// refuses the server side echo and echoes the input on its own.
+	pub fn set_echo(&mut self, echo: bool) {
+		self.options.echo = echo;
+	}
+
+	/// Start of the word in front of the cursor, skipping the spaces right before the cursor
+	fn word_start(&self) -> usize {
+		let mut start = self.cursor;
+		while start > 0 && self.line_buffer[start - 1] == ' ' {
+			start -= 1;
+		}
+		while start > 0 && self.line_buffer[start - 1] != ' ' {
+			start -= 1;
+		}
+		start
+	}
+
+	/// End of the word after the cursor, skipping the spaces right after the cursor
+	fn word_end(&self) -> usize {
+		let mut end = self.cursor;
+		while end < self.line_buffer.len() && self.line_buffer[end] == ' ' {
+			end += 1;
+		}
+		while end < self.line_buffer.len() && self.line_buffer[end] != ' ' {
+			end += 1;
+		}
+		end
+	}
+
+	/// Insert the next (or previous) completion candidate in place of the completed part
+	/// of the line, wrapping around at the ends of the list.
+	fn cycle_completion<T: CharacterTerminalWriter>(&mut self, forward: bool, terminal: &mut T) {
+		let (index, completed) = match self.autocomplete {
+			AutocompleteRequest::HaveMultipleOptions { ref lines, selected } if !lines.is_empty() => {
+				let count = lines.len();
+				let index = match (selected, forward) {
+					(Some(i), true) => (i + 1) % count,
+					(Some(i), false) => (i + count - 1) % count,
+					(None, true) => 0,
+					(None, false) => count - 1
+				};
+				(index, lines[index].full_new_line.chars().collect::<Vec<_>>())
+			},
+			_ => { return; }
+		};
+
+		if completed.len() + self.line_buffer.len() - self.cursor > self.options.max_line_length {
+			self.reject_input(terminal);
+			return;
+		}
+
+		if let AutocompleteRequest::HaveMultipleOptions { ref mut selected, .. } = self.autocomplete {
+			*selected = Some(index);
+		}
+
+		let end = self.cursor;
+		let from = self.column(end);
+		self.cursor = completed.len();
+		self.line_buffer.splice(..end, completed);
+		if self.options.echo {
+			self.redraw_line(terminal, from);
+		}
+	}
+
+	/// Notify the user that the input would make the line longer than allowed.
+	fn reject_input<T: CharacterTerminalWriter>(&self, terminal: &mut T) {
+		match self.options.input_rejected_feedback {
+			InputRejectedFeedback::None => (),
+			InputRejectedFeedback::Bell => {
+				terminal.print(&[0x07]);
+			},
+			InputRejectedFeedback::Message => {
+				terminal.newline();
+				self.strings.line_too_long(terminal, self.options.max_line_length).ok();
+				terminal.newline();
+				self.redraw_line(terminal, 0);
+			}
+		}
+	}
+
+	/// Keys while the reverse history search is active. Returns false if the key should
+	/// be processed by the regular line editor, after the search has been accepted.
+	fn handle_search_key<T: CharacterTerminalWriter>(&mut self, key: Key, terminal: &mut T) -> bool {
+		let mut search = match self.search.take() {
+			Some(search) => search,
+			None => { return false; }
+		};
+
+		// where to look for the next match, the current match is still a candidate
+		let from = match key {
+			Key::Character(c) => {
+				search.query.push(c);
+				search.found.as_ref().map(|f| f.0 + 1).unwrap_or(self.history.len())
+			},
+			Key::Backspace => {
+				search.query.pop();
+				self.history.len()
+			},
+			Key::Control('r') => {
+				search.found.as_ref().map(|f| f.0).unwrap_or(self.history.len())
+			},
+			Key::Escape | Key::Control('g') => {
+				// abort, restore the line that was being edited
+				self.line_buffer = search.original_line;
+				self.cursor = search.original_cursor;
+				if self.options.echo {
+					self.redraw_line(terminal, 0);
+				}
+				return true;
+			},
+			_ => {
+				// accept the match and let the editor handle the key
+				if let Some((_, line)) = search.found {
+					self.replace_line(&line, terminal, 0);
+				} else {
+					self.line_buffer = search.original_line;
+					self.cursor = search.original_cursor;
+					if self.options.echo {
+						self.redraw_line(terminal, 0);
+					}
+				}
+				return false;
+			}
+		};
+
+		match self.history.search(&search.query, from) {
+			Some((i, line)) => {
+				search.found = Some((i, line.to_string()));
+				search.failed = false;
+			},
+			None => {
+				search.failed = true;
+			}
+		}
+
+		if self.options.echo {
+			self.print_search(&search, terminal);
+		}
+
+		self.search = Some(search);
+		true
+	}
+
+	fn print_search<T: CharacterTerminalWriter>(&self, search: &HistorySearch, terminal: &mut T) {
+		let line = search.found.as_ref().map(|f| f.1.as_str()).unwrap_or("");
+
+		terminal.print_str("\r");
+		self.strings.history_search(terminal, &search.query, line, !search.failed).ok();
+		terminal.print_str("\x1b[K");
+	}
+
 	/// Handle a single key from a terminal. Blocks until the terminal implementation returns a key.
 	pub fn handle_terminal_key<T, F: FnOnce(&mut CliExecutor) -> ()>(&mut self, terminal: &mut T, call_commands: F) -> Result<PromptEvent, TerminalError>
 		where T: CharacterTerminalWriter + CharacterTerminalReader + FmtWrite
@@ -115,6 +745,33 @@ impl PromptBuffer {
 	{
 		let mut handled_autocomplete = false;
 
+		match key {
+			Key::CursorPosition { row, column } => {
+				// a position report that doesn't reply to `query_terminal_size` isn't the size
+				if self.size_query_pending {
+					self.size_query_pending = false;
+					self.set_terminal_size(column, row);
+				}
+				return PromptEvent::Ok;
+			},
+			Key::Modified(_, ModifiedKey::Function(3)) if self.size_query_pending => {
+				// the reply of a terminal with a single row, decoded as F3
+				self.size_query_pending = false;
+				return PromptEvent::Ok;
+			},
+			Key::WindowSize { rows, columns } => {
+				self.set_terminal_size(columns, rows);
+				return PromptEvent::Ok;
+			},
+			_ => {
+				// an actual key press
+			}
+		}
+
+		if self.handle_search_key(key, terminal) {
+			return PromptEvent::Ok;
+		}
+
 		let is_line_finished = {
 			match self.options.newline_key_sequence {
 				NewlineSequence::Newline => key == Key::Newline,
@@ -129,44 +786,111 @@ impl PromptBuffer {
 			
 			terminal.print_line("");
 
-			if let Ok(line) = str::from_utf8(self.line_buffer.as_slice()) {
-				
-				let result = {
-					let mut matcher = CliLineMatcher::new(&line, LineMatcherMode::Execute);
-					let mut executor = CliExecutor::new(matcher, &*self.strings, terminal);
-					call_commands(&mut executor);
-					executor.close().finish()
-				};
-
-				match result {
-					LineBufferResult::NoMatchFound => {
-						if line.trim().len() > 0 {
-							// command not recognized
-							self.strings.cmd_not_recognized(terminal, line.trim());
+			let action = match self.pending_question.take() {
+				Some(pending) => {
+					match self.parse_answer(pending.get_question()) {
+						Some(answer) => {
+							LineAction::Answer { asked: pending, answer: answer }
+						},
+						None => {
+							// ask again
+							self.strings.question_invalid_answer(terminal, pending.get_question()).ok();
 							terminal.newline();
+							self.pending_question = Some(pending);
+							LineAction::None
 						}
-					},
-					_ => ()
+					}
+				},
+				None => {
+					let line = self.line();
+
+					if self.history.push(&line) {
+						if let Some(ref mut store) = self.history_store {
+							// a failing store shouldn't prevent the execution of the line
+							let _ = store.append(&line);
+						}
+					}
+
+					let trimmed = line.trim();
+					if self.change_path_enabled && (trimmed == "cd" || trimmed.starts_with("cd ")) {
+						LineAction::ChangePath { path: trimmed[2..].trim().into() }
+					} else if self.options.help_enabled && (trimmed == "help" || trimmed.starts_with("help ")) {
+						LineAction::Help { arg: trimmed[4..].trim().into() }
+					} else {
+						LineAction::Execute { line: line.clone() }
+					}
 				}
+			};
 
+			match action {
+				LineAction::None => (),
+				LineAction::ChangePath { path } => {
+					self.change_path(&path, terminal, call_commands);
+				},
+				LineAction::Help { arg } => {
+					self.print_help(&arg, terminal, call_commands);
+				},
+				LineAction::Answer { asked, answer } => {
+					// only the command's handler gets the answer, the line isn't executed again
+					let current_path = self.get_current_path();
+					if let Some(question) = asked.answer(answer, terminal, &current_path) {
+						self.ask(question, terminal);
+					}
+				},
+				LineAction::Execute { line } => {
+					let (resolved_line, _, _) = self.resolve_line(&line);
+					let current_path = self.get_current_path();
+
+					let (result, question) = {
+						let mut matcher = CliLineMatcher::new(&resolved_line, LineMatcherMode::Execute);
+						let mut executor = CliExecutor::new(matcher, &*self.strings, terminal);
+						executor.set_current_path(&current_path);
+						call_commands(&mut executor);
+						let question = executor.take_question();
+						(executor.close().finish(), question)
+					};
+
+					match result {
+						LineBufferResult::NoMatchFound => {
+							if line.trim().len() > 0 {
+								// command not recognized
+								self.strings.cmd_not_recognized(terminal, line.trim());
+								terminal.newline();
+							}
+						},
+						_ => ()
+					}
+
+					if let Some(question) = question {
+						self.ask(question, terminal);
+					}
+				}
 			}
 
 			self.line_buffer.clear();
+			self.cursor = 0;
 			self.print_prompt(terminal);
 
 		} else {
 			match key {
-				Key::Tab => {
+				Key::Tab | Key::Modified(_, ModifiedKey::Tab) if self.reading_input() => {
+					// the answers can't be autocompleted
+				},
+				Key::Tab | Key::Modified(KeyModifiers { shift: true, .. }, ModifiedKey::Tab) => {
+					let reverse = key != Key::Tab;
 
 					match self.autocomplete {
 						AutocompleteRequest::None => {
 							
-							let mut single_match_additional_chars = None;
+							let mut single_match = None;
 
-							if let Ok(line) = str::from_utf8(self.line_buffer.as_slice()) {
+							// complete the part of the line in front of the cursor
+							{
+								let line: String = self.line_buffer[..self.cursor].iter().collect();
+								let (resolved_line, matcher_prefix, line_prefix) = self.resolve_line(&line);
 
 								let result = {
-									let matcher = CliLineMatcher::new(&line, LineMatcherMode::AutocompleteOnly);
+									let matcher = CliLineMatcher::new(&resolved_line, LineMatcherMode::AutocompleteOnly);
 									let mut executor = CliExecutor::new(matcher, &*self.strings, terminal);
 									call_commands(&mut executor);
 									executor.close().finish()
@@ -177,12 +901,8 @@ impl PromptBuffer {
 										match result {
 											AutocompleteResult::None => (),
 											AutocompleteResult::SingleMatch { line } => {
-												// immediately send the new stuff
-												terminal.print_str(line.get_additional_part());
-
 												// clear the line outside the borrowed content
-												single_match_additional_chars = Some(line.full_new_line);
-												
+												single_match = Some(line.replace_prefix(&matcher_prefix, &line_prefix));
 											},
 											AutocompleteResult::MultipleMatches { lines } => {
 												// this was the first time tab was pressed, and there are multiple options. store them,
@@ -190,7 +910,8 @@ impl PromptBuffer {
 												// we could also bleep at this point...
 
 												self.autocomplete = AutocompleteRequest::HaveMultipleOptions {
-													lines: lines
+													lines: lines.iter().map(|l| l.replace_prefix(&matcher_prefix, &line_prefix)).collect(),
+													selected: None
 												};
 											}
 										}
@@ -199,28 +920,58 @@ impl PromptBuffer {
 								}
 							}
 
-							if let Some(single_match_additional_chars) = single_match_additional_chars.take() {
+							let too_long = single_match.as_ref().map_or(false, |l| {
+								l.full_new_line.chars().count() + self.line_buffer.len() - self.cursor > self.options.max_line_length
+							});
+
+							if too_long {
+								single_match = None;
+								self.reject_input(terminal);
+							}
+
+							if let Some(single_match) = single_match.take() {
+								// a quoted argument value rewrites the word in front of the cursor
+								let extends_line = single_match.full_new_line.chars().take(self.cursor).eq(self.line_buffer[..self.cursor].iter().cloned());
+								let old_column = self.column(self.cursor);
+
 								// replace our line buffer with the stuff from autocomplete, to be consistent with future invokations
+								let tail = self.line_buffer.split_off(self.cursor);
 								self.line_buffer.clear();
-								for c in single_match_additional_chars.bytes() {
-									self.line_buffer.push(c);
+								self.line_buffer.extend(single_match.full_new_line.chars());
+								self.cursor = self.line_buffer.len();
+								self.line_buffer.extend_from_slice(&tail);
+
+								if extends_line {
+									// immediately send the new stuff
+									let additional: Vec<char> = single_match.get_additional_part().chars().collect();
+									let from = self.column(self.cursor) - chars_display_width(&additional);
+									self.print_at(terminal, &additional, from);
+									self.redraw_tail(terminal, 0);
+								} else {
+									self.redraw_line(terminal, old_column);
 								}
 							}
 
+							if reverse {
+								self.cycle_completion(false, terminal);
+							}
+
+							handled_autocomplete = true;
+						},
+						AutocompleteRequest::HaveMultipleOptions { selected, .. } if reverse || selected.is_some() => {
+							self.cycle_completion(!reverse, terminal);
 							handled_autocomplete = true;
-						},					
-						AutocompleteRequest::HaveMultipleOptions { ref lines } => {
+						},
+						AutocompleteRequest::HaveMultipleOptions { ref lines, .. } => {
 							// print the available autocomplete options
 
 							terminal.print_line("");
 							
 							let suggestions = lines.iter().map(|l| { l.get_display() }).collect::<Vec<&str>>();
-							format_in_columns(suggestions.as_slice(), 80, 4, &self.options.newline, terminal);
-
-							self.print_prompt(terminal);
+							format_in_columns(suggestions.as_slice(), self.terminal_size.0, 4, &self.options.newline, terminal);
 
 							// restore the current buffer
-							terminal.print(&self.line_buffer);
+							self.redraw_line(terminal, 0);
 
 							handled_autocomplete = false;
 						}
@@ -232,34 +983,204 @@ impl PromptBuffer {
 					// newline keys				
 				},
 				Key::Backspace => {
-					if let Some(..) = self.line_buffer.pop() {
+					if self.cursor > 0 {
+						self.cursor -= 1;
+						let width = self.remove_at_cursor();
+
+						if self.options.echo {
+							let column = self.column(self.cursor);
+							self.move_cursor(terminal, column + width, column);
+							self.redraw_tail(terminal, width);
+						}
+					}
+				},
+				Key::Delete => {
+					if self.cursor < self.line_buffer.len() {
+						let width = self.remove_at_cursor();
+
 						if self.options.echo {
-							terminal.print(&[0x08, 0x20, 0x08]);
+							self.redraw_tail(terminal, width);
 						}
 					}
 				},
 				Key::Break => {
-					if self.line_buffer.len() == 0 {
+					if self.line_buffer.len() == 0 && !self.reading_input() {
 						return PromptEvent::Break;
 					}
 
+					// also cancels the command's question
+					self.pending_question = None;
+
 					// clear the line
 					self.line_buffer.clear();
+					self.cursor = 0;
+					self.history.reset_navigation();
+					terminal.print_line("");
+					self.print_prompt(terminal);
+				},
+				Key::Eot if self.line_buffer.is_empty() && self.reading_input() => {
+					// cancel the command's question
+					self.pending_question = None;
 					terminal.print_line("");
 					self.print_prompt(terminal);
 				},
 				Key::Eot => {
-					return PromptEvent::Break;
+					if self.line_buffer.is_empty() {
+						return PromptEvent::Break;
+					}
+
+					// behaves as delete on a non-empty line
+					if self.cursor < self.line_buffer.len() {
+						let width = self.remove_at_cursor();
+
+						if self.options.echo {
+							self.redraw_tail(terminal, width);
+						}
+					}
+				},
+				Key::Control('a') | Key::Home => {
+					// move to the start of the line
+					if self.options.echo {
+						self.move_cursor(terminal, self.column(self.cursor), self.column(0));
+					}
+					self.cursor = 0;
+				},
+				Key::Control('e') | Key::End => {
+					// move to the end of the line
+					if self.options.echo {
+						self.print_at(terminal, &self.line_buffer[self.cursor..], self.column(self.cursor));
+					}
+					self.cursor = self.line_buffer.len();
 				},
-				Key::Arrow(_) => {
-					// todo: line history?
+				Key::Control('k') => {
+					// kill to the end of the line
+					self.line_buffer.truncate(self.cursor);
+
+					if self.options.echo {
+						terminal.print_str("\x1b[J");
+					}
+				},
+				Key::Control('u') => {
+					// kill to the start of the line
+					let from = self.column(self.cursor);
+					self.line_buffer.drain(..self.cursor);
+					self.cursor = 0;
+
+					if self.options.echo {
+						self.redraw_line(terminal, from);
+					}
+				},
+				Key::Control('w') | Key::Modified(KeyModifiers { alt: true, .. }, ModifiedKey::Backspace) => {
+					// delete the word in front of the cursor, along with the spaces that follow it
+					let start = self.word_start();
+					let from = self.column(self.cursor);
+
+					self.line_buffer.drain(start..self.cursor);
+					self.cursor = start;
+
+					if self.options.echo {
+						self.redraw_line(terminal, from);
+					}
+				},
+				Key::Control('l') => {
+					// clear the screen and redraw the line at the top
+					if self.options.echo {
+						terminal.print_str("\x1b[2J\x1b[H");
+						self.redraw_line(terminal, 0);
+					}
+				},
+				Key::Control('r') if !self.reading_input() => {
+					self.history.reset_navigation();
+
+					let search = HistorySearch {
+						query: String::new(),
+						found: None,
+						failed: false,
+						original_line: self.line_buffer.clone(),
+						original_cursor: self.cursor
+					};
+
+					if self.options.echo {
+						// the search is shown on the first row of the line
+						let from = self.column(self.cursor);
+						self.move_cursor(terminal, from, from % self.terminal_columns());
+						self.print_search(&search, terminal);
+					}
+
+					self.search = Some(search);
+				},
+				Key::Modified(KeyModifiers { ctrl: true, .. }, ModifiedKey::Arrow(DirectionKey::Left)) |
+				Key::Modified(KeyModifiers { alt: true, .. }, ModifiedKey::Arrow(DirectionKey::Left)) |
+				Key::Modified(KeyModifiers { alt: true, .. }, ModifiedKey::Character('b')) => {
+					// move to the start of the previous word
+					let start = self.word_start();
+					if self.options.echo {
+						self.move_cursor(terminal, self.column(self.cursor), self.column(start));
+					}
+					self.cursor = start;
+				},
+				Key::Modified(KeyModifiers { ctrl: true, .. }, ModifiedKey::Arrow(DirectionKey::Right)) |
+				Key::Modified(KeyModifiers { alt: true, .. }, ModifiedKey::Arrow(DirectionKey::Right)) |
+				Key::Modified(KeyModifiers { alt: true, .. }, ModifiedKey::Character('f')) => {
+					// move to the end of the next word
+					let end = self.word_end();
+					if self.options.echo {
+						self.print_at(terminal, &self.line_buffer[self.cursor..end], self.column(self.cursor));
+					}
+					self.cursor = end;
+				},
+				Key::Control(_) | Key::Modified(..) | Key::Escape | Key::Insert | Key::PageUp | Key::PageDown | Key::Function(_) |
+				Key::CursorPosition { .. } | Key::WindowSize { .. } => {
+					// not bound to anything
+				},
+				Key::Arrow(DirectionKey::Up) | Key::Arrow(DirectionKey::Down) if self.reading_input() => {
+					// the answers aren't a part of the history
+				},
+				Key::Arrow(DirectionKey::Up) => {
+					let line = self.line();
+					if let Some(previous) = self.history.older(&line).map(|l| l.to_string()) {
+						let from = self.column(self.cursor);
+						self.replace_line(&previous, terminal, from);
+					}
+				},
+				Key::Arrow(DirectionKey::Down) => {
+					if let Some(next) = self.history.newer().map(|l| l.to_string()) {
+						let from = self.column(self.cursor);
+						self.replace_line(&next, terminal, from);
+					}
+				},
+				Key::Arrow(DirectionKey::Left) => {
+					if self.cursor > 0 {
+						self.cursor -= 1;
+
+						if self.options.echo {
+							self.move_cursor(terminal, self.column(self.cursor + 1), self.column(self.cursor));
+						}
+					}
+				},
+				Key::Arrow(DirectionKey::Right) => {
+					if self.cursor < self.line_buffer.len() {
+						if self.options.echo {
+							// reprinting the character moves the cursor over it
+							self.print_at(terminal, &self.line_buffer[self.cursor..self.cursor + 1], self.column(self.cursor));
+						}
+
+						self.cursor += 1;
+					}
+				},
+				Key::Character('?') if self.options.contextual_help && !self.reading_input() && !self.in_open_quote() => {
+					self.contextual_help(terminal, call_commands);
 				},
 				Key::Character(c) => {
-					if c != '\r' as u8 {
-						self.line_buffer.push(c);
+					if self.line_buffer.len() >= self.options.max_line_length {
+						self.reject_input(terminal);
+					} else if c != '\r' {
+						self.line_buffer.insert(self.cursor, c);
+						self.cursor += 1;
 
 						if self.options.echo {
-							terminal.print(&[c]);
+							self.print_at(terminal, &[c], self.column(self.cursor - 1));
+							self.redraw_tail(terminal, 0);
 						}
 					}
 				}
@@ -273,4 +1194,36 @@ impl PromptBuffer {
 
 		PromptEvent::Ok
 	}
-}
\ No newline at end of file
+}
+
+fn print_chars<T: CharacterTerminalWriter>(terminal: &mut T, chars: &[char]) {
+	let mut buf = [0; 4];
+	for c in chars {
+		terminal.print_str(c.encode_utf8(&mut buf));
+	}
+}
+
+/// Collects the printed output, to measure it
+struct StringWriter(String);
+
+impl CharacterTerminalWriter for StringWriter {
+	fn print(&mut self, bytes: &[u8]) {
+		self.0.push_str(&String::from_utf8_lossy(bytes));
+	}
+}
+
+impl FmtWrite for StringWriter {
+	fn write_str(&mut self, s: &str) -> Result<(), FmtError> {
+		self.0.push_str(s);
+		Ok(())
+	}
+}
+
+/// Move the terminal's cursor to the left by the given number of columns
+fn cursor_left<T: CharacterTerminalWriter>(terminal: &mut T, columns: usize) {
+	match columns {
+		0 => (),
+		1 => terminal.print(&[0x08]),
+		n => terminal.print_str(&format!("\x1b[{}D", n))
+	}
+}
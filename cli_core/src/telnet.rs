@@ -0,0 +1,319 @@
+use prelude::v1::*;
+
+/// Interpret As Command, starts every telnet command
+pub const IAC: u8 = 255;
+pub const DONT: u8 = 254;
+pub const DO: u8 = 253;
+pub const WONT: u8 = 252;
+pub const WILL: u8 = 251;
+/// Subnegotiation begin
+pub const SB: u8 = 250;
+/// Interrupt process
+pub const IP: u8 = 244;
+/// Subnegotiation end
+pub const SE: u8 = 240;
+
+/// Option: the server echoes the typed characters
+pub const OPTION_ECHO: u8 = 1;
+/// Option: suppress go ahead, for character at a time input
+pub const OPTION_SUPPRESS_GO_AHEAD: u8 = 3;
+/// Option: negotiate about window size
+pub const OPTION_NAWS: u8 = 31;
+
+/// Longest subnegotiation that is collected, the rest is dropped
+const MAX_SUBNEGOTIATION_LENGTH: usize = 32;
+
+/// Negotiation state of a single option on one side of the connection
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+struct OptionState {
+	enabled: bool,
+	/// We asked for the option and wait for the reply
+	requested: bool
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum ParserState {
+	Data,
+	Iac,
+	/// Waiting for the option of a WILL, WONT, DO or DONT command
+	Negotiation(u8),
+	Subnegotiation,
+	SubnegotiationIac
+}
+
+/// Result of processing a chunk of the network input
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TelnetInput {
+	/// The data sent by the client with the telnet commands stripped, ready for the `TerminalKeyDecoder`
+	pub data: Vec<u8>,
+	/// Negotiation replies to be sent back to the client
+	pub response: Vec<u8>,
+	/// Window size in columns and rows, if the client reported a new one
+	pub window_size: Option<(u16, u16)>
+}
+
+/// Server side of the telnet protocol, for remote consoles. Negotiates the server side
+/// echo, character at a time input and the window size reports, and separates the
+/// commands from the data. Doesn't do any I/O on its own.
+pub struct TelnetProtocol {
+	state: ParserState,
+	subnegotiation: Vec<u8>,
+	local_echo: OptionState,
+	local_suppress_go_ahead: OptionState,
+	remote_suppress_go_ahead: OptionState,
+	remote_naws: OptionState,
+	window_size: Option<(u16, u16)>,
+	/// The output ended with a carriage return, encoded once the next byte is known
+	output_cr_pending: bool
+}
+
+impl Default for TelnetProtocol {
+	fn default() -> TelnetProtocol {
+		TelnetProtocol::new()
+	}
+}
+
+impl TelnetProtocol {
+	pub fn new() -> TelnetProtocol {
+		TelnetProtocol {
+			state: ParserState::Data,
+			subnegotiation: Vec::new(),
+			local_echo: Default::default(),
+			local_suppress_go_ahead: Default::default(),
+			remote_suppress_go_ahead: Default::default(),
+			remote_naws: Default::default(),
+			window_size: None,
+			output_cr_pending: false
+		}
+	}
+
+	/// The negotiation to send right after the client connects. The server offers to echo
+	/// the input and to suppress the go aheads, and asks the client for its window size.
+	pub fn hello(&mut self) -> Vec<u8> {
+		let mut output = vec![];
+
+		for &(command, option) in &[(WILL, OPTION_ECHO), (WILL, OPTION_SUPPRESS_GO_AHEAD),
+		                             (DO, OPTION_SUPPRESS_GO_AHEAD), (DO, OPTION_NAWS)] {
+			if let Some(state) = self.option_state(command == WILL, option) {
+				if !state.enabled {
+					state.requested = true;
+					output.extend_from_slice(&[IAC, command, option]);
+				}
+			}
+		}
+
+		output
+	}
+
+	/// Is the server echoing the input? If the client refused, it echoes the typed
+	/// characters on its own and the prompt shouldn't.
+	pub fn is_echo_enabled(&self) -> bool {
+		self.local_echo.enabled
+	}
+
+	/// The last window size reported by the client, in columns and rows
+	pub fn get_window_size(&self) -> Option<(u16, u16)> {
+		self.window_size
+	}
+
+	/// Process bytes received from the network.
+	pub fn handle_input(&mut self, input: &[u8]) -> TelnetInput {
+		let mut result = TelnetInput::default();
+
+		for &byte in input {
+			self.state = match (self.state, byte) {
+				(ParserState::Data, IAC) => ParserState::Iac,
+				(ParserState::Data, b) => {
+					result.data.push(b);
+					ParserState::Data
+				},
+				(ParserState::Iac, IAC) => {
+					// escaped 0xFF
+					result.data.push(IAC);
+					ParserState::Data
+				},
+				(ParserState::Iac, c @ WILL..=DONT) => ParserState::Negotiation(c),
+				(ParserState::Iac, SB) => {
+					self.subnegotiation.clear();
+					ParserState::Subnegotiation
+				},
+				(ParserState::Iac, IP) => {
+					// Ctrl-C sent as a telnet command
+					result.data.push(0x03);
+					ParserState::Data
+				},
+				(ParserState::Iac, _) => ParserState::Data,
+				(ParserState::Negotiation(command), option) => {
+					self.negotiate(command, option, &mut result.response);
+					ParserState::Data
+				},
+				(ParserState::Subnegotiation, IAC) => ParserState::SubnegotiationIac,
+				(ParserState::Subnegotiation, b) | (ParserState::SubnegotiationIac, b @ IAC) => {
+					if self.subnegotiation.len() < MAX_SUBNEGOTIATION_LENGTH {
+						self.subnegotiation.push(b);
+					}
+					ParserState::Subnegotiation
+				},
+				(ParserState::SubnegotiationIac, SE) => {
+					self.end_subnegotiation(&mut result);
+					ParserState::Data
+				},
+				(ParserState::SubnegotiationIac, _) => ParserState::Subnegotiation
+			};
+		}
+
+		result
+	}
+
+	/// Prepare the terminal output for the network. Escapes the 0xFF bytes and sends
+	/// a carriage return that isn't followed by a line feed as CR NUL. A carriage return
+	/// at the end of the data is held back until the next call or `flush_output`.
+	pub fn encode_output(&mut self, data: &[u8], output: &mut Vec<u8>) {
+		for &b in data {
+			if self.output_cr_pending {
+				self.output_cr_pending = false;
+				if b == b'\n' {
+					output.extend_from_slice(b"\r\n");
+					continue;
+				}
+				output.extend_from_slice(b"\r\0");
+			}
+
+			match b {
+				IAC => output.extend_from_slice(&[IAC, IAC]),
+				b'\r' => self.output_cr_pending = true,
+				b => output.push(b)
+			}
+		}
+	}
+
+	/// Send the carriage return held back by `encode_output`, once no more output follows
+	pub fn flush_output(&mut self, output: &mut Vec<u8>) {
+		if self.output_cr_pending {
+			self.output_cr_pending = false;
+			output.extend_from_slice(b"\r\0");
+		}
+	}
+
+	/// State of the options we support, `None` for the ones we always refuse. `local`
+	/// options are performed by the server, the others by the client.
+	fn option_state(&mut self, local: bool, option: u8) -> Option<&mut OptionState> {
+		match (local, option) {
+			(true, OPTION_ECHO) => Some(&mut self.local_echo),
+			(true, OPTION_SUPPRESS_GO_AHEAD) => Some(&mut self.local_suppress_go_ahead),
+			(false, OPTION_SUPPRESS_GO_AHEAD) => Some(&mut self.remote_suppress_go_ahead),
+			(false, OPTION_NAWS) => Some(&mut self.remote_naws),
+			_ => None
+		}
+	}
+
+	fn negotiate(&mut self, command: u8, option: u8, response: &mut Vec<u8>) {
+		let (agree, refuse) = match command {
+			DO | DONT => (WILL, WONT),
+			_ => (DO, DONT)
+		};
+		let enable = command == DO || command == WILL;
+
+		// DO and DONT are about our options, WILL and WONT about the client's
+		let local = command == DO || command == DONT;
+
+		let reply = match self.option_state(local, option) {
+			Some(state) => {
+				let requested = state.requested;
+				state.requested = false;
+
+				if state.enabled == enable {
+					// already in the requested state, don't reply to avoid loops
+					None
+				} else {
+					state.enabled = enable;
+					if requested {
+						// the reply to our own request
+						None
+					} else if enable {
+						Some(agree)
+					} else {
+						Some(refuse)
+					}
+				}
+			},
+			// we don't support the option, refuse to enable it
+			None if enable => Some(refuse),
+			None => None
+		};
+
+		if let Some(reply) = reply {
+			response.extend_from_slice(&[IAC, reply, option]);
+		}
+	}
+
+	fn end_subnegotiation(&mut self, result: &mut TelnetInput) {
+		if let [OPTION_NAWS, w1, w2, h1, h2] = self.subnegotiation[..] {
+			let size = ((w1 as u16) << 8 | w2 as u16, (h1 as u16) << 8 | h2 as u16);
+			self.window_size = Some(size);
+			result.window_size = Some(size);
+		}
+
+		self.subnegotiation.clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_telnet_negotiation() {
+		let mut telnet = TelnetProtocol::new();
+		assert_eq!(vec![IAC, WILL, OPTION_ECHO, IAC, WILL, OPTION_SUPPRESS_GO_AHEAD,
+		                IAC, DO, OPTION_SUPPRESS_GO_AHEAD, IAC, DO, OPTION_NAWS], telnet.hello());
+		assert!(!telnet.is_echo_enabled());
+
+		// replies to our requests are acknowledged silently, unknown options refused
+		let input = telnet.handle_input(&[IAC, DO, OPTION_ECHO, IAC, DO, OPTION_SUPPRESS_GO_AHEAD,
+		                                  IAC, WILL, OPTION_SUPPRESS_GO_AHEAD, IAC, WILL, OPTION_NAWS,
+		                                  IAC, WILL, 24, IAC, DO, 0]);
+		assert!(telnet.is_echo_enabled());
+		assert_eq!(vec![IAC, DONT, 24, IAC, WONT, 0], input.response);
+		assert!(input.data.is_empty());
+
+		// repeated requests don't cause a reply
+		assert!(telnet.handle_input(&[IAC, DO, OPTION_ECHO]).response.is_empty());
+
+		// client turns off our echo
+		let input = telnet.handle_input(&[IAC, DONT, OPTION_ECHO]);
+		assert!(!telnet.is_echo_enabled());
+		assert_eq!(vec![IAC, WONT, OPTION_ECHO], input.response);
+	}
+
+	#[test]
+	fn test_telnet_data() {
+		let mut telnet = TelnetProtocol::new();
+
+		// the subnegotiation and the commands are stripped from the data
+		let input = telnet.handle_input(&[b'a', IAC, SB, OPTION_NAWS, 0, 120, 0, 40, IAC, SE, b'b', IAC, IAC, IAC, 241, b'c']);
+		assert_eq!(vec![b'a', b'b', IAC, b'c'], input.data);
+		assert_eq!(Some((120, 40)), input.window_size);
+
+		// split over multiple reads, with an escaped 0xFF in the size
+		assert_eq!(None, telnet.handle_input(&[IAC, SB, OPTION_NAWS, 1, IAC]).window_size);
+		let input = telnet.handle_input(&[IAC, 0, 30, IAC, SE, IAC, IP]);
+		assert_eq!(Some((511, 30)), input.window_size);
+		assert_eq!(Some((511, 30)), telnet.get_window_size());
+		assert_eq!(vec![0x03], input.data);
+
+		let mut output = vec![];
+		telnet.encode_output(b"\xff\r\n\r# ", &mut output);
+		assert_eq!(b"\xff\xff\r\n\r\0# ".to_vec(), output);
+
+		// the line feed of a split CR LF comes with the next write
+		let mut output = vec![];
+		telnet.encode_output(b"a\r", &mut output);
+		assert_eq!(b"a".to_vec(), output);
+		telnet.encode_output(b"\nb\r", &mut output);
+		assert_eq!(b"a\r\nb".to_vec(), output);
+		telnet.flush_output(&mut output);
+		telnet.flush_output(&mut output);
+		assert_eq!(b"a\r\nb\r\0".to_vec(), output);
+	}
+}
@@ -86,3 +86,784 @@ pub fn test_suggest() {
 		
 	}
 }
+
+
+/// Collects everything written to the terminal
+pub struct TestTerminal {
+	pub output: Vec<u8>
+}
+
+impl TestTerminal {
+	pub fn new() -> TestTerminal {
+		TestTerminal { output: Vec::new() }
+	}
+}
+
+impl CharacterTerminalWriter for TestTerminal {
+	fn print(&mut self, bytes: &[u8]) {
+		self.output.extend_from_slice(bytes);
+	}
+}
+
+impl FmtWrite for TestTerminal {
+	fn write_str(&mut self, s: &str) -> Result<(), FmtError> {
+		self.print(s.as_bytes());
+		Ok(())
+	}
+}
+
+fn type_str(prompt: &mut PromptBuffer, terminal: &mut TestTerminal, s: &str) {
+	for c in s.chars() {
+		prompt.handle_key(Key::Character(c), terminal, |_| {});
+	}
+}
+
+/// Press the keys of the script with the same commands for every key, returns the
+/// output printed meanwhile
+fn run_keys<F>(prompt: &mut PromptBuffer, terminal: &mut TestTerminal, keys: &str, mut call_commands: F) -> String
+	where F: FnMut(&mut CliExecutor)
+{
+	terminal.output.clear();
+	for key in parse_key_script(keys).unwrap() {
+		prompt.handle_key(key, terminal, |m| call_commands(m));
+	}
+	String::from_utf8(terminal.output.clone()).unwrap()
+}
+
+#[test]
+pub fn test_prompt_history() {
+	let mut terminal = TestTerminal::new();
+	let mut prompt = PromptBuffer::new(Default::default());
+	let mut executed = vec![];
+
+	for line in &["first", "second"] {
+		type_str(&mut prompt, &mut terminal, line);
+		prompt.handle_key(Key::Newline, &mut terminal, |m| {
+			if let Some(ctx) = m.command("first") { executed.push(ctx.get_args().to_string()); }
+			if let Some(ctx) = m.command("second") { executed.push(ctx.get_args().to_string()); }
+		});
+	}
+
+	type_str(&mut prompt, &mut terminal, "draft");
+	prompt.handle_key(Key::Arrow(DirectionKey::Up), &mut terminal, |_| {});
+	prompt.handle_key(Key::Arrow(DirectionKey::Up), &mut terminal, |_| {});
+	prompt.handle_key(Key::Arrow(DirectionKey::Down), &mut terminal, |_| {});
+	prompt.handle_key(Key::Arrow(DirectionKey::Down), &mut terminal, |_| {});
+	assert!(terminal.output.ends_with(b"\r# draft\x1b[J"));
+
+	prompt.handle_key(Key::Arrow(DirectionKey::Up), &mut terminal, |_| {});
+	prompt.handle_key(Key::Arrow(DirectionKey::Up), &mut terminal, |_| {});
+	type_str(&mut prompt, &mut terminal, " args");
+	prompt.handle_key(Key::Newline, &mut terminal, |m| {
+		if let Some(ctx) = m.command("first") { executed.push(ctx.get_args().to_string()); }
+	});
+
+	assert_eq!(vec!["", "", "args"], executed);
+	assert_eq!(vec!["first", "second", "first args"], prompt.get_history().iter().collect::<Vec<_>>());
+}
+
+#[test]
+pub fn test_prompt_history_store() {
+	let mut terminal = TestTerminal::new();
+	let mut prompt = PromptBuffer::new(Default::default());
+
+	let mut store = MemoryHistoryStore::new(10);
+	store.append("p1/hello").unwrap();
+	store.append("counter/set 5").unwrap();
+	prompt.set_history_store(Box::new(store)).unwrap();
+	assert_eq!(vec!["p1/hello", "counter/set 5"], prompt.get_history().iter().collect::<Vec<_>>());
+
+	let path = ::std::env::temp_dir().join(format!("terminal_cli_prompt_history_{}", ::std::process::id()));
+	let _ = ::std::fs::remove_file(&path);
+	let mut prompt = PromptBuffer::new(Default::default());
+	prompt.set_history_store(Box::new(FileHistoryStore::new(path.clone(), 10))).unwrap();
+
+	type_str(&mut prompt, &mut terminal, "p2/hello");
+	prompt.handle_key(Key::Newline, &mut terminal, |_| {});
+	assert_eq!(Ok(vec!["p2/hello".to_string()]), FileHistoryStore::new(path.clone(), 10).load());
+
+	let mut prompt = PromptBuffer::new(Default::default());
+	prompt.set_history_store(Box::new(FileHistoryStore::new(path.clone(), 10))).unwrap();
+	prompt.handle_key(Key::Arrow(DirectionKey::Up), &mut terminal, |_| {});
+	assert!(terminal.output.ends_with(b"\r# p2/hello\x1b[J"));
+
+	::std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+pub fn test_prompt_cursor_editing() {
+	let mut terminal = TestTerminal::new();
+	let mut prompt = PromptBuffer::new(Default::default());
+	let mut executed = vec![];
+
+	type_str(&mut prompt, &mut terminal, "p1/hxy world!");
+	for _ in 0..9 {
+		prompt.handle_key(Key::Arrow(DirectionKey::Left), &mut terminal, |_| {});
+	}
+	prompt.handle_key(Key::Arrow(DirectionKey::Right), &mut terminal, |_| {});
+	prompt.handle_key(Key::Backspace, &mut terminal, |_| {});
+	prompt.handle_key(Key::Delete, &mut terminal, |_| {});
+	prompt.handle_key(Key::Tab, &mut terminal, |m| {
+		m.command("p1/hello");
+		m.command("p2/hello");
+	});
+	assert!(terminal.output.ends_with(b"ello world!\x1b[7D"));
+
+	prompt.handle_key(Key::Newline, &mut terminal, |m| {
+		if let Some(ctx) = m.command("p1/hello") { executed.push(ctx.get_args().to_string()); }
+	});
+	assert_eq!(vec!["world!"], executed);
+}
+
+#[test]
+pub fn test_prompt_control_keys() {
+	let mut terminal = TestTerminal::new();
+	let mut prompt = PromptBuffer::new(Default::default());
+	let mut executed = vec![];
+
+	type_str(&mut prompt, &mut terminal, "xcmd one two three");
+	prompt.handle_key(Key::Control('w'), &mut terminal, |_| {});
+	prompt.handle_key(Key::Control('a'), &mut terminal, |_| {});
+	assert_eq!(PromptEvent::Ok, prompt.handle_key(Key::Eot, &mut terminal, |_| {}));
+	prompt.handle_key(Key::Control('e'), &mut terminal, |_| {});
+	type_str(&mut prompt, &mut terminal, "four five");
+	for _ in 0..5 {
+		prompt.handle_key(Key::Arrow(DirectionKey::Left), &mut terminal, |_| {});
+	}
+	prompt.handle_key(Key::Control('k'), &mut terminal, |_| {});
+	prompt.handle_key(Key::Newline, &mut terminal, |m| {
+		if let Some(ctx) = m.command("cmd") { executed.push(ctx.get_args().to_string()); }
+	});
+	assert_eq!(vec!["one two four"], executed);
+
+	type_str(&mut prompt, &mut terminal, "abc");
+	prompt.handle_key(Key::Control('u'), &mut terminal, |_| {});
+	assert_eq!(PromptEvent::Break, prompt.handle_key(Key::Eot, &mut terminal, |_| {}));
+}
+
+#[test]
+pub fn test_prompt_modified_keys() {
+	let mut terminal = TestTerminal::new();
+	let mut prompt = PromptBuffer::new(Default::default());
+	let mut executed = vec![];
+
+	let word_left = Key::Modified(KeyModifiers::CTRL, ModifiedKey::Arrow(DirectionKey::Left));
+	let word_right = Key::Modified(KeyModifiers::ALT, ModifiedKey::Character('f'));
+
+	type_str(&mut prompt, &mut terminal, "cmd one three");
+	prompt.handle_key(word_left, &mut terminal, |_| {});
+	prompt.handle_key(word_left, &mut terminal, |_| {});
+	prompt.handle_key(word_right, &mut terminal, |_| {});
+	type_str(&mut prompt, &mut terminal, " two");
+	prompt.handle_key(word_right, &mut terminal, |_| {});
+	prompt.handle_key(word_right, &mut terminal, |_| {});
+	type_str(&mut prompt, &mut terminal, " four");
+	prompt.handle_key(Key::Modified(KeyModifiers::ALT, ModifiedKey::Backspace), &mut terminal, |_| {});
+	prompt.handle_key(Key::Newline, &mut terminal, |m| {
+		if let Some(ctx) = m.command("cmd") { executed.push(ctx.get_args().to_string()); }
+	});
+	assert_eq!(vec!["one two three "], executed);
+
+	// Shift+Tab cycles backwards through the candidates, Tab then moves forward
+	let commands = |m: &mut CliExecutor| {
+		m.command("test/alpha");
+		m.command("test/beta");
+		m.command("test/gamma");
+	};
+	let back_tab = Key::Modified(KeyModifiers::SHIFT, ModifiedKey::Tab);
+
+	type_str(&mut prompt, &mut terminal, "test/");
+	prompt.handle_key(back_tab, &mut terminal, commands);
+	prompt.handle_key(back_tab, &mut terminal, commands);
+	prompt.handle_key(Key::Tab, &mut terminal, commands);
+	prompt.handle_key(Key::Newline, &mut terminal, |m| {
+		if m.command("test/gamma").is_some() { executed.push("gamma".into()); }
+	});
+	assert_eq!(vec!["one two three ", "gamma"], executed);
+}
+
+#[test]
+pub fn test_prompt_key_script() {
+	let mut terminal = TestTerminal::new();
+	let mut prompt = PromptBuffer::new(Default::default());
+	let mut executed = vec![];
+
+	for key in parse_key_script("cmd xtwo<C-Left><Del>one <Enter>").unwrap() {
+		prompt.handle_key(key, &mut terminal, |m| {
+			if let Some(ctx) = m.command("cmd") { executed.push(ctx.get_args().to_string()); }
+		});
+	}
+	assert_eq!(vec!["one two"], executed);
+}
+
+#[test]
+pub fn test_prompt_terminal_size() {
+	let mut terminal = TestTerminal::new();
+	let mut prompt = PromptBuffer::new(Default::default());
+	assert_eq!((80, 24), prompt.get_terminal_size());
+
+	let mut decoder = TerminalKeyDecoder::new();
+	let mut report = |prompt: &mut PromptBuffer, terminal: &mut TestTerminal, bytes: &[u8]| {
+		for b in bytes {
+			if let Ok(key) = decoder.decode(*b) {
+				prompt.handle_key(key, terminal, |_| {});
+			}
+		}
+	};
+
+	// a cursor position report is the size only as a reply to the query
+	report(&mut prompt, &mut terminal, b"\x1b[5;10R");
+	assert_eq!((80, 24), prompt.get_terminal_size());
+
+	prompt.query_terminal_size(&mut terminal);
+	assert_eq!(b"\x1b7\x1b[999;999H\x1b[6n\x1b8".to_vec(), terminal.output);
+	terminal.output.clear();
+	report(&mut prompt, &mut terminal, b"\x1b[5;10R\x1b[6;12R");
+	assert_eq!((10, 5), prompt.get_terminal_size());
+
+	report(&mut prompt, &mut terminal, b"\x1b[8;4;20t");
+	assert_eq!((20, 4), prompt.get_terminal_size());
+
+	// a single row terminal replies with what reads as F3
+	prompt.query_terminal_size(&mut terminal);
+	terminal.output.clear();
+	report(&mut prompt, &mut terminal, b"\x1b[1;40R\x1b[5;10R");
+	assert_eq!((20, 4), prompt.get_terminal_size());
+	assert!(terminal.output.is_empty());
+
+	prompt.set_terminal_size(10, 5);
+
+	// "# abcdefgh" fills the first row, the cursor is moved to the second one
+	type_str(&mut prompt, &mut terminal, "abcdefgh");
+	assert!(terminal.output.ends_with(b"h\r\n"));
+	prompt.handle_key(Key::Arrow(DirectionKey::Left), &mut terminal, |_| {});
+	assert!(terminal.output.ends_with(b"\x1b[1A\x1b[9C"));
+	type_str(&mut prompt, &mut terminal, "x");
+	assert!(terminal.output.ends_with(b"x\r\nh\x08"));
+	prompt.handle_key(Key::Control('a'), &mut terminal, |_| {});
+	assert!(terminal.output.ends_with(b"\x1b[1A\x1b[2C"));
+}
+
+#[test]
+pub fn test_prompt_history_search() {
+	let mut terminal = TestTerminal::new();
+	let mut prompt = PromptBuffer::new(Default::default());
+	prompt.get_history().push("p1/hello");
+	prompt.get_history().push("counter/set 5");
+	prompt.get_history().push("p2/hello");
+
+	let mut executed = vec![];
+
+	type_str(&mut prompt, &mut terminal, "abc");
+	prompt.handle_key(Key::Control('r'), &mut terminal, |_| {});
+	type_str(&mut prompt, &mut terminal, "hel");
+	assert!(terminal.output.ends_with(b"\r(reverse-i-search)'hel': p2/hello\x1b[K"));
+	prompt.handle_key(Key::Control('r'), &mut terminal, |_| {});
+	assert!(terminal.output.ends_with(b"\r(reverse-i-search)'hel': p1/hello\x1b[K"));
+	prompt.handle_key(Key::Control('r'), &mut terminal, |_| {});
+	assert!(terminal.output.ends_with(b"\r(failed reverse-i-search)'hel': p1/hello\x1b[K"));
+	prompt.handle_key(Key::Newline, &mut terminal, |m| {
+		if m.command("p1/hello").is_some() { executed.push("p1"); }
+		if m.command("p2/hello").is_some() { executed.push("p2"); }
+	});
+	assert_eq!(vec!["p1"], executed);
+
+	type_str(&mut prompt, &mut terminal, "abc");
+	prompt.handle_key(Key::Control('r'), &mut terminal, |_| {});
+	type_str(&mut prompt, &mut terminal, "set");
+	prompt.handle_key(Key::Control('g'), &mut terminal, |_| {});
+	assert!(terminal.output.ends_with(b"\r# abc\x1b[J"));
+
+	// a lone ESC from the terminal also cancels the search
+	let mut decoder = TerminalKeyDecoder::new();
+	let mut keys: Vec<Key> = b"\x12set\x1b".iter().filter_map(|b| decoder.decode(*b).ok()).collect();
+	keys.extend(decoder.flush());
+	assert_eq!(Some(&Key::Escape), keys.last());
+	for key in keys {
+		prompt.handle_key(key, &mut terminal, |_| {});
+	}
+	assert!(terminal.output.ends_with(b"\r# abc\x1b[J"));
+}
+
+#[test]
+pub fn test_prompt_utf8_editing() {
+	let mut terminal = TestTerminal::new();
+	let mut prompt = PromptBuffer::new(Default::default());
+	let mut executed = vec![];
+
+	type_str(&mut prompt, &mut terminal, "set é");
+	prompt.handle_key(Key::Backspace, &mut terminal, |_| {});
+	assert!(terminal.output.ends_with(b"\x08 \x08"));
+
+	type_str(&mut prompt, &mut terminal, "日x");
+	prompt.handle_key(Key::Arrow(DirectionKey::Left), &mut terminal, |_| {});
+	prompt.handle_key(Key::Arrow(DirectionKey::Left), &mut terminal, |_| {});
+	assert!(terminal.output.ends_with(b"\x1b[2D"));
+	type_str(&mut prompt, &mut terminal, "ü");
+	assert!(terminal.output.ends_with("ü日x\x1b[3D".as_bytes()));
+
+	prompt.handle_key(Key::Newline, &mut terminal, |m| {
+		if let Some(ctx) = m.command("set") { executed.push(ctx.get_args().to_string()); }
+	});
+	assert_eq!(vec!["ü日x"], executed);
+}
+
+#[test]
+pub fn test_prompt_max_line_length() {
+	let mut terminal = TestTerminal::new();
+	let options = PromptBufferOptions { max_line_length: 8, ..Default::default() };
+	let mut prompt = PromptBuffer::new(options);
+	let mut executed = vec![];
+
+	type_str(&mut prompt, &mut terminal, "cmd 1234");
+	assert!(!terminal.output.contains(&0x07));
+	type_str(&mut prompt, &mut terminal, "5");
+	assert!(terminal.output.ends_with(b"4\x07"));
+	prompt.handle_key(Key::Newline, &mut terminal, |m| {
+		if let Some(ctx) = m.command("cmd") { executed.push(ctx.get_args().to_string()); }
+	});
+	assert_eq!(vec!["1234"], executed);
+
+	// "p1/hello" fits exactly, "p1/hello2" would not
+	type_str(&mut prompt, &mut terminal, "p1/h");
+	prompt.handle_key(Key::Tab, &mut terminal, |m| { m.command("p1/hello"); });
+	assert!(terminal.output.ends_with(b"ello"));
+	prompt.handle_key(Key::Control('u'), &mut terminal, |_| {});
+	type_str(&mut prompt, &mut terminal, "p2/h");
+	prompt.handle_key(Key::Tab, &mut terminal, |m| { m.command("p2/hello2"); });
+	assert!(terminal.output.ends_with(b"p2/h\x07"));
+}
+
+#[test]
+pub fn test_prompt_max_line_length_message() {
+	let mut terminal = TestTerminal::new();
+	let options = PromptBufferOptions {
+		max_line_length: 2,
+		input_rejected_feedback: InputRejectedFeedback::Message,
+		..Default::default()
+	};
+	let mut prompt = PromptBuffer::new(options);
+
+	type_str(&mut prompt, &mut terminal, "abc");
+	assert_eq!(&b"ab\r\nLine is too long, the maximum length is 2 characters.\r\n\r# ab\x1b[J"[..], &terminal.output[..]);
+}
+
+#[test]
+pub fn test_prompt_secret_input() {
+	let mut terminal = TestTerminal::new();
+	let mut prompt = PromptBuffer::new(Default::default());
+	let passwords = Rc::new(RefCell::new(vec![]));
+
+	{
+		let mut run = |prompt: &mut PromptBuffer, terminal: &mut TestTerminal, key: Key| {
+			prompt.handle_key(key, terminal, |m| {
+				if let Some(mut ctx) = m.command("login") {
+					let passwords = passwords.clone();
+					ctx.request_secret_input("Password: ", move |password, _| passwords.borrow_mut().push(password));
+				}
+			})
+		};
+
+		for c in "login".chars() {
+			run(&mut prompt, &mut terminal, Key::Character(c));
+		}
+		run(&mut prompt, &mut terminal, Key::Newline);
+		assert!(terminal.output.ends_with(b"\r\nPassword: "));
+
+		for c in "pw".chars() {
+			run(&mut prompt, &mut terminal, Key::Character(c));
+		}
+		run(&mut prompt, &mut terminal, Key::Tab);
+		run(&mut prompt, &mut terminal, Key::Arrow(DirectionKey::Up));
+		assert!(terminal.output.ends_with(b"Password: **"));
+		run(&mut prompt, &mut terminal, Key::Newline);
+		assert!(terminal.output.ends_with(b"\r\n# "));
+
+		// cancelled with break
+		for c in "login".chars() {
+			run(&mut prompt, &mut terminal, Key::Character(c));
+		}
+		run(&mut prompt, &mut terminal, Key::Newline);
+		assert_eq!(PromptEvent::Ok, run(&mut prompt, &mut terminal, Key::Break));
+		run(&mut prompt, &mut terminal, Key::Newline);
+	}
+
+	assert_eq!(vec!["pw"], *passwords.borrow());
+	assert_eq!(vec!["login"], prompt.get_history().iter().collect::<Vec<_>>());
+}
+
+#[test]
+pub fn test_prompt_questions() {
+	let mut terminal = TestTerminal::new();
+	let mut prompt = PromptBuffer::new(Default::default());
+	let results = Rc::new(RefCell::new(vec![]));
+	let mut executed = 0;
+
+	for line in &["reset", "maybe", "y", "mode", "3", "fast", "empty", "login", "joe", "secret"] {
+		for c in line.chars() {
+			prompt.handle_key(Key::Character(c), &mut terminal, |_| {});
+		}
+		prompt.handle_key(Key::Newline, &mut terminal, |m| {
+			if let Some(mut ctx) = m.command("reset") {
+				executed += 1;
+				let results = results.clone();
+				ctx.confirm("Are you sure?", false, move |yes, _| results.borrow_mut().push(Answer::Confirm(yes)));
+			}
+			if let Some(mut ctx) = m.command("mode") {
+				executed += 1;
+				let results = results.clone();
+				ctx.choose("Select the mode", &["slow", "fast"], move |index, _| results.borrow_mut().push(Answer::Choice(index)));
+			}
+			if let Some(mut ctx) = m.command("empty") {
+				if !ctx.choose("Select the network", &[], |_, _| {}) {
+					ctx.get_terminal().print_line("No networks found.");
+				}
+			}
+			if let Some(mut ctx) = m.command("login") {
+				// the handler asks the next question
+				ctx.request_input("User: ", InputEcho::Visible, |_, ctx| {
+					ctx.request_secret_input("Password: ", |_, ctx| {
+						let line = format!("Logged in as {}.", ctx.get_inputs()[0]);
+						ctx.get_terminal().print_line(&line);
+					});
+				});
+			}
+		});
+	}
+
+	let output = String::from_utf8(terminal.output.clone()).unwrap();
+	assert!(output.contains("\r\nAre you sure? [y/N] maybe\r\nPlease answer yes or no.\r\nAre you sure? [y/N] y\r\n# "));
+	assert!(output.contains("Select the mode\r\n  1) slow\r\n  2) fast\r\nChoice [1-2]: 3"));
+	assert!(output.contains("Please pick one of the listed choices.\r\nChoice [1-2]: fast\r\n# "));
+	assert!(output.contains("# empty\r\nNo networks found.\r\n# "));
+	assert!(output.ends_with("# login\r\nUser: joe\r\nPassword: ******\r\nLogged in as joe.\r\n# "));
+	assert_eq!(vec![Answer::Confirm(true), Answer::Choice(1)], *results.borrow());
+	// the commands ran once, only their handlers got the answers
+	assert_eq!(2, executed);
+}
+
+#[test]
+pub fn test_prompt_change_path() {
+	let mut terminal = TestTerminal::new();
+	let options = PromptBufferOptions { prompt: "\\W# ".into(), change_path_enabled: true, ..Default::default() };
+	let mut prompt = PromptBuffer::new(options);
+	let mut executed = vec![];
+
+	{
+		let mut commands = |m: &mut CliExecutor| {
+			if let Some(ctx) = m.command("p1/hello") { executed.push(format!("hello in '{}'", ctx.get_current_path())); }
+			if let Some(ctx) = m.command("p1/s1/reset") { executed.push(format!("reset in '{}'", ctx.get_current_path())); }
+			if let Some(ctx) = m.command("top") { executed.push(format!("top in '{}'", ctx.get_current_path())); }
+		};
+
+		assert!(run_keys(&mut prompt, &mut terminal, "cd p1/s1<Enter>", &mut commands).ends_with("\r\n/p1/s1/# "));
+		run_keys(&mut prompt, &mut terminal, "reset<Enter>cd ..<Enter>", &mut commands);
+		assert!(run_keys(&mut prompt, &mut terminal, "hel<Tab>", &mut commands).ends_with("hello"));
+		run_keys(&mut prompt, &mut terminal, "<Enter>", &mut commands);
+		assert!(run_keys(&mut prompt, &mut terminal, "/t<Tab>", &mut commands).ends_with("/top"));
+		run_keys(&mut prompt, &mut terminal, "<Enter>", &mut commands);
+		assert_eq!("top\r\nCommand not recognized.\r\n/p1/# ", run_keys(&mut prompt, &mut terminal, "top<Enter>", &mut commands));
+		assert!(run_keys(&mut prompt, &mut terminal, "cd nothing<Enter>", &mut commands).ends_with("\r\nPath 'nothing' not found.\r\n/p1/# "));
+		assert_eq!("cd /\r\n/# top\r\n/# ", run_keys(&mut prompt, &mut terminal, "cd /<Enter>top<Enter>", &mut commands));
+	}
+
+	assert_eq!(vec!["reset in 'p1/s1'", "hello in 'p1'", "top in 'p1'", "top in ''"], executed);
+}
+
+#[test]
+pub fn test_execute_line() {
+	let mut num1 = 1;
+	let mut connected = vec![];
+
+	let mut run = |line: &str| {
+		let mut terminal = TestTerminal::new();
+		let result = execute_line(line, &i18n::English, &mut terminal, |m| {
+			if let Some(mut ctx) = m.command("p1/hello") {
+				ctx.get_terminal().print_line("Hello");
+			}
+			if let Some(mut ctx) = m.command("fail") {
+				ctx.get_terminal().print_line("Something went wrong.");
+				ctx.fail();
+			}
+			if let Some(mut ctx) = m.command("reset") {
+				ctx.confirm("Are you sure?", false, |_, _| {});
+			}
+			if let Some(mut ctx) = m.property("num1", validate_property_min_max(1, 100)) {
+				ctx.apply(&mut num1);
+			}
+			if let Some(ctx) = m.command("wifi/connect") {
+				connected = ctx.iter_args().collect::<Vec<_>>();
+			}
+		});
+		(result, String::from_utf8(terminal.output).unwrap())
+	};
+
+	assert_eq!((ExecutionResult::Success, "Hello\r\n".into()), run("p1/hello"));
+	assert_eq!((ExecutionResult::Success, "New value for num1 is 5.\r\n".into()), run("num1/set 5"));
+	assert_eq!((ExecutionResult::Failed, "Something went wrong.\r\n".into()), run("fail"));
+	assert_eq!(ExecutionResult::Failed, run("num1/set 500").0);
+	assert_eq!((ExecutionResult::NotRecognized, "Command not recognized.\r\n".into()), run("p1/nothing"));
+	assert_eq!(ExecutionResult::InputRequired, run("reset").0);
+
+	// the process arguments are quoted, an argument with a space stays a single one
+	let line = args_to_line(vec!["wifi/connect", "home network", "it's"]);
+	assert_eq!("wifi/connect \"home network\" \"it's\"", line);
+	assert_eq!(ExecutionResult::Success, run(&line).0);
+	assert_eq!(vec!["home network", "it's"], connected);
+
+	assert_eq!(0, ExecutionResult::Success.exit_code());
+	assert_eq!(2, ExecutionResult::NotRecognized.exit_code());
+	assert_eq!(5, num1);
+	assert_eq!("num1/set 5", args_to_line(vec!["num1/set", "5"]));
+}
+
+#[test]
+pub fn test_prompt_help() {
+	let mut terminal = TestTerminal::new();
+	let options = PromptBufferOptions { prompt: "\\W# ".into(), change_path_enabled: true, help_enabled: true, ..Default::default() };
+	let mut prompt = PromptBuffer::new(options);
+	let mut num1 = 1;
+	let mut executed = 0;
+
+	let mut commands = |m: &mut CliExecutor| {
+		if let Some(mut m) = m.with_prefix("p1/") {
+			if m.command_with_help("hello", "Say hello.\nPrints a greeting to the terminal.").is_some() { executed += 1; }
+			if m.command("reset").is_some() { executed += 1; }
+		}
+		if let Some(mut ctx) = m.property_with_help("num1", validate_property_min_max(1, 100), "A number between 1 and 100") {
+			ctx.apply(&mut num1);
+		}
+	};
+
+	assert_eq!("help\r\np1/hello  Say hello.\r\np1/reset\r\nnum1/get  A number between 1 and 100\r\nnum1/set  A number between 1 and 100\r\n/# ",
+	           run_keys(&mut prompt, &mut terminal, "help<Enter>", &mut commands));
+	assert_eq!("help p1/hello\r\np1/hello\r\n  Say hello.\r\n  Prints a greeting to the terminal.\r\n/# ",
+	           run_keys(&mut prompt, &mut terminal, "help p1/hello<Enter>", &mut commands));
+	assert_eq!("help num\r\nnum1/get  A number between 1 and 100\r\nnum1/set  A number between 1 and 100\r\n/# ",
+	           run_keys(&mut prompt, &mut terminal, "help num<Enter>", &mut commands));
+	assert_eq!("help nothing\r\nCommand not recognized.\r\n/# ", run_keys(&mut prompt, &mut terminal, "help nothing<Enter>", &mut commands));
+
+	// relative to the current path
+	run_keys(&mut prompt, &mut terminal, "cd p1<Enter>", &mut commands);
+	assert_eq!("help\r\nhello  Say hello.\r\nreset\r\n/p1/# ", run_keys(&mut prompt, &mut terminal, "help<Enter>", &mut commands));
+	assert!(run_keys(&mut prompt, &mut terminal, "help /<Enter>", &mut commands).contains("\r\n/num1/set  A number"));
+
+	assert_eq!(0, executed);
+	assert_eq!(1, num1);
+}
+
+#[test]
+pub fn test_prompt_contextual_help() {
+	let mut terminal = TestTerminal::new();
+	let options = PromptBufferOptions { contextual_help: true, ..Default::default() };
+	let mut prompt = PromptBuffer::new(options);
+	let mut executed = 0;
+
+	let mut commands = |m: &mut CliExecutor| {
+		if m.command_with_help("p1/hello", "Say hello.\nPrints a greeting.").is_some() { executed += 1; }
+		if m.command("p1/reset").is_some() { executed += 1; }
+		if m.command_with_help("ping", "Check the connection.").is_some() { executed += 1; }
+	};
+
+	// `?` isn't inserted, the list is followed by the redrawn line
+	assert_eq!("\r\np1/                            ping  Check the connection.\r\n\r# \x1b[J",
+	           run_keys(&mut prompt, &mut terminal, "?", &mut commands));
+	assert_eq!("p1/\r\nhello  Say hello.    reset                \r\n\r# p1/\x1b[J",
+	           run_keys(&mut prompt, &mut terminal, "p1/?", &mut commands));
+
+	// only the part in front of the cursor is matched, the cursor is restored
+	assert_eq!("\x08\x08\x1b[2C\r\np1/                            ping  Check the connection.\r\n\r# p1/\x1b[J\x1b[2D",
+	           run_keys(&mut prompt, &mut terminal, "<Left><Left>?", &mut commands));
+	assert_eq!("1/x\r\nCommand not recognized.\r\n\r# p1/x\x1b[J", run_keys(&mut prompt, &mut terminal, "<End>x?", &mut commands));
+
+	// a complete command lists itself, followed by a space it can be executed as it is
+	assert_eq!("\r# \x1b[Jp1/hello\r\nhello  Say hello.    \r\n\r# p1/hello\x1b[J", run_keys(&mut prompt, &mut terminal, "<C-u>p1/hello?", &mut commands));
+	assert_eq!("\r# \x1b[Jp1/hello a\r\n<cr>  Say hello.    \r\n\r# p1/hello a\x1b[J", run_keys(&mut prompt, &mut terminal, "<C-u>p1/hello a?", &mut commands));
+	assert_eq!(0, executed);
+}
+
+#[test]
+pub fn test_prompt_contextual_help_arguments() {
+	let schema = ArgumentSchema::new()
+		.arg(Argument::positional("text").required().help("What to say"))
+		.arg(Argument::flag("loud").help("In capitals"));
+
+	let mut terminal = TestTerminal::new();
+	let options = PromptBufferOptions { contextual_help: true, ..Default::default() };
+	let mut prompt = PromptBuffer::new(options);
+	let mut said = vec![];
+
+	let mut commands = |m: &mut CliExecutor| {
+		if let Some(ctx) = m.command_with_args("say", "Print the text.", &schema) {
+			said.push(ctx.get_arguments().get_str("text").unwrap_or("").to_string());
+		}
+	};
+
+	// `<cr>` only once the required arguments are there
+	assert_eq!("say \r\nUsage: say <text> [--loud]\r\n--loud  In capitals    \r\n\r# say \x1b[J",
+	           run_keys(&mut prompt, &mut terminal, "say ?", &mut commands));
+	assert_eq!("hi \r\nUsage: say <text> [--loud]\r\n--loud  In capitals        <cr>    Print the text.    \r\n\r# say hi \x1b[J",
+	           run_keys(&mut prompt, &mut terminal, "hi ?", &mut commands));
+
+	// inside the quotes `?` is a part of the argument
+	assert_eq!("\r# \x1b[Jsay \"what?\"", run_keys(&mut prompt, &mut terminal, "<C-u>say \"what?\"", &mut commands));
+	run_keys(&mut prompt, &mut terminal, "<Enter>", &mut commands);
+	assert_eq!(vec!["what?"], said);
+}
+
+#[test]
+pub fn test_command_arguments() {
+	let schema = ArgumentSchema::new()
+		.arg(Argument::positional("ssid").help("Network name"))
+		.arg(Argument::option("channel").value(validate_property_min_max(1u8, 14)).help("Radio channel"))
+		.arg(Argument::flag("hidden").help("Don't broadcast the name"));
+
+	let mut connected = None;
+
+	{
+		let mut run = |line: &str| {
+			let mut terminal = TestTerminal::new();
+			let result = execute_line(line, &i18n::English, &mut terminal, |m| {
+				if let Some(ctx) = m.command_with_args("wifi/connect", "Connect to a network.", &schema) {
+					let args = ctx.get_arguments();
+					connected = Some((args.get::<String>("ssid").unwrap(), args.get::<u8>("channel"), args.has("hidden")));
+				}
+			});
+			(result, String::from_utf8(terminal.output).unwrap())
+		};
+
+		assert_eq!((ExecutionResult::Success, "".to_string()), run("wifi/connect home --channel 6 --hidden"));
+		assert_eq!((ExecutionResult::Failed, "Missing the required argument <ssid>.\r\nUsage: wifi/connect <ssid> [--channel <channel>] [--hidden]\r\n".to_string()),
+		           run("wifi/connect --hidden"));
+		assert_eq!((ExecutionResult::Failed, "Unknown option --speed.\r\nUsage: wifi/connect <ssid> [--channel <channel>] [--hidden]\r\n".to_string()),
+		           run("wifi/connect home --speed 5"));
+		assert!(run("wifi/connect home --channel 20").1.starts_with("--channel: Value 20 is too large, the maximum value is 14.\r\n"));
+	}
+
+	assert_eq!(Some(("home".to_string(), Some(6), true)), connected);
+
+	// the options are completed and the schema is part of the help
+	let mut terminal = TestTerminal::new();
+	let options = PromptBufferOptions { help_enabled: true, ..Default::default() };
+	let mut prompt = PromptBuffer::new(options);
+
+	let mut commands = |m: &mut CliExecutor| {
+		m.command_with_args("wifi/connect", "Connect to a network.", &schema);
+	};
+
+	assert_eq!("wifi/connect home --channel", run_keys(&mut prompt, &mut terminal, "wifi/connect home --c<Tab>", &mut commands));
+	run_keys(&mut prompt, &mut terminal, "<Enter>", &mut commands);
+	assert_eq!("help wifi/connect\r\nwifi/connect\r\n  Connect to a network.\r\n  wifi/connect <ssid> [--channel <channel>] [--hidden]\r\n  \
+	            <ssid>     Network name\r\n  --channel  Radio channel\r\n  --hidden   Don't broadcast the name\r\n# ",
+	           run_keys(&mut prompt, &mut terminal, "help wifi/connect<Enter>", &mut commands));
+}
+
+#[test]
+pub fn test_quoted_arguments() {
+	let schema = ArgumentSchema::new()
+		.arg(Argument::positional("ssid"))
+		.arg(Argument::option("password"));
+
+	let mut executed = vec![];
+	{
+		let mut terminal = TestTerminal::new();
+		let line = r#"wifi/connect "home network" --password='p4ss w0rd' tag\ one "tag \"two\"""#;
+		execute_line(line, &i18n::English, &mut terminal, |m| {
+			if let Some(ctx) = m.command("tag") {
+				executed.push(ctx.iter_args().collect::<Vec<_>>());
+			}
+			if let Some(ctx) = m.command_with_args("wifi/connect", "", &schema) {
+				let args = ctx.get_arguments();
+				executed.push(vec![args.get_str("ssid").unwrap().to_string(), args.get_str("password").unwrap().to_string()]);
+			}
+		});
+		assert_eq!("Unexpected argument 'tag one'.\r\nUsage: wifi/connect <ssid> [--password <password>]\r\n", String::from_utf8(terminal.output.clone()).unwrap());
+
+		execute_line(r#"tag "one two" three\ four 'five"'"#, &i18n::English, &mut terminal, |m| {
+			if let Some(ctx) = m.command("tag") {
+				executed.push(ctx.iter_args().collect::<Vec<_>>());
+			}
+		});
+		execute_line(r#"wifi/connect 'home network' --password "p4ss w0rd""#, &i18n::English, &mut terminal, |m| {
+			if let Some(ctx) = m.command_with_args("wifi/connect", "", &schema) {
+				let args = ctx.get_arguments();
+				executed.push(vec![args.get_str("ssid").unwrap().to_string(), args.get_str("password").unwrap().to_string()]);
+			}
+		});
+	}
+	assert_eq!(vec![vec!["one two", "three four", "five\""], vec!["home network", "p4ss w0rd"]], executed);
+
+	// the values are completed inside an open quote, and quoted when they contain spaces
+	let complete = |line: &str| {
+		let mut matcher = CliLineMatcher::new(line, LineMatcherMode::AutocompleteOnly);
+		matcher.match_argument_values("wifi/connect", &[("home network", None), ("home lab", None), ("office", None)]);
+		match matcher.finish() {
+			LineBufferResult::Autocomplete { result: AutocompleteResult::SingleMatch { line } } => vec![line.full_new_line],
+			LineBufferResult::Autocomplete { result: AutocompleteResult::MultipleMatches { lines } } => lines.into_iter().map(|l| l.full_new_line).collect(),
+			_ => vec![]
+		}
+	};
+
+	assert_eq!(vec!["wifi/connect \"home network\""], complete("wifi/connect \"home n"));
+	assert_eq!(vec!["wifi/connect 'home network'"], complete("wifi/connect 'home n"));
+	assert_eq!(vec!["wifi/connect \"home network\""], complete("wifi/connect home\\ n"));
+	assert_eq!(vec!["wifi/connect \"home "], complete("wifi/connect \"home"));
+	assert_eq!(vec!["wifi/connect \"home lab\"", "wifi/connect \"home network\""], complete("wifi/connect \"home "));
+	assert_eq!(vec!["wifi/connect office"], complete("wifi/connect o"));
+}
+
+#[test]
+pub fn test_argument_completion() {
+	let devices = vec!["lamp".to_string(), "living room".to_string(), "heater".to_string()];
+	let device_names = CompletionProvider::callback(|_| devices.clone());
+	let bools = CompletionProvider::list(vec!["true", "false"]);
+	let schema = ArgumentSchema::new()
+		.arg(Argument::positional("device").complete(CompletionProvider::callback(|_| devices.clone())))
+		.arg(Argument::option("level").complete(CompletionProvider::list(vec!["low", "medium", "high"])))
+		.arg(Argument::flag("force"));
+
+	let mut terminal = TestTerminal::new();
+	let mut prompt = PromptBuffer::new(Default::default());
+	let mut switch = false;
+
+	let mut commands = |m: &mut CliExecutor| {
+		m.command_with_completion("on", "", &device_names);
+		if let Some(mut ctx) = m.property_with_completion("switch", ValueBool, "", &bools) {
+			ctx.apply(&mut switch);
+		}
+		m.command_with_args("set", "", &schema);
+	};
+
+	assert_eq!("on li\r# on \"living room\"\x1b[J", run_keys(&mut prompt, &mut terminal, "on li<Tab>", &mut commands));
+	// the line is cleared first, the redraw is the `\r# \x1b[J` in front
+	assert_eq!("\r# \x1b[Jon l\r\n\"living room\"    lamp             \r\n\r# on l\x1b[J", run_keys(&mut prompt, &mut terminal, "<C-u>on l<Tab><Tab>", &mut commands));
+	assert_eq!("\r# \x1b[Jswitch/set true\r\nNew value for switch is true.\r\n# ", run_keys(&mut prompt, &mut terminal, "<C-u>switch/set t<Tab><Enter>", &mut commands));
+
+	// the argument at the position of the word, or the value of the option in front of it
+	assert_eq!("\r# \x1b[Jset heater", run_keys(&mut prompt, &mut terminal, "<C-u>set h<Tab>", &mut commands));
+	assert_eq!("\r# \x1b[Jset heater --level high", run_keys(&mut prompt, &mut terminal, "<C-u>set heater --level h<Tab>", &mut commands));
+	assert_eq!("\r# \x1b[Jset heater --", run_keys(&mut prompt, &mut terminal, "<C-u>set heater <Tab>", &mut commands));
+	assert_eq!("\r# \x1b[Jset heater -- l", run_keys(&mut prompt, &mut terminal, "<C-u>set heater -- l<Tab>", &mut commands));
+	assert!(switch);
+}
+
+#[test]
+pub fn test_prompt_carriage_return_sequence() {
+	let mut terminal = TestTerminal::new();
+	let options = PromptBufferOptions { newline_key_sequence: NewlineSequence::CarriageReturn, ..Default::default() };
+	let mut prompt = PromptBuffer::new(options);
+	let mut decoder = TerminalKeyDecoder::new();
+	let mut executed = vec![];
+
+	// only the `\r` ends the line, a `\n` on its own doesn't
+	for b in b"one\r\ntwo\n three\r" {
+		if let Ok(key) = decoder.decode(*b) {
+			prompt.handle_key(key, &mut terminal, |m| {
+				if let Some(ctx) = m.command("one") { executed.push(ctx.get_args().to_string()); }
+				if let Some(ctx) = m.command("two") { executed.push(ctx.get_args().to_string()); }
+			});
+		}
+	}
+
+	assert_eq!(vec!["".to_string(), "three".to_string()], executed);
+}
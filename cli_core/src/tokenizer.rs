@@ -0,0 +1,169 @@
+use prelude::v1::*;
+
+/// Split the arguments of a command into words. The words are separated by whitespace.
+/// Double quotes group the words and understand backslash escapes, single quotes keep
+/// everything literally. Outside of the quotes, a backslash escapes the next character.
+///
+/// ```
+/// # use terminal_cli::*;
+/// let args: Vec<String> = tokenize_arguments(r#"home\ lab "guest \"net\"" 'C:\temp'"#).collect();
+/// assert_eq!(vec!["home lab", "guest \"net\"", "C:\\temp"], args);
+/// ```
+pub fn tokenize_arguments<'a>(args: &'a str) -> ArgumentTokens<'a> {
+	ArgumentTokens {
+		args: args,
+		position: 0
+	}
+}
+
+/// The word at the end of the arguments, the one that is being typed. Empty if the
+/// arguments end with whitespace.
+pub fn partial_argument(args: &str) -> ArgumentToken {
+	let mut tokens = tokenize_arguments(args);
+	while let Some(token) = tokens.next_token() {
+		if token.end == args.len() {
+			return token;
+		}
+	}
+
+	ArgumentToken {
+		start: args.len(),
+		end: args.len(),
+		value: String::new(),
+		open_quote: None
+	}
+}
+
+/// Quote the value so that it is read back as a single word. The value is enclosed in
+/// the given quote, or in double quotes if it contains whitespace or special characters.
+pub fn quote_argument(value: &str, quote: Option<char>) -> String {
+	let needs_quotes = value.is_empty() || value.chars().any(|c| c.is_whitespace() || c == '"' || c == '\'' || c == '\\');
+
+	match quote {
+		Some('\'') => format!("'{}'", value.replace('\'', "'\\''")),
+		Some(_) => format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"")),
+		None if needs_quotes => quote_argument(value, Some('"')),
+		None => value.to_string()
+	}
+}
+
+/// A word of the arguments, see `tokenize_arguments`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArgumentToken {
+	/// Byte offset of the word in the arguments, including the opening quote
+	pub start: usize,
+	/// Byte offset after the end of the word
+	pub end: usize,
+	/// The word without the quotes and the escapes
+	pub value: String,
+	/// The quote that wasn't closed before the end of the arguments
+	pub open_quote: Option<char>
+}
+
+/// Iterator over the words of the arguments
+pub struct ArgumentTokens<'a> {
+	args: &'a str,
+	position: usize
+}
+
+impl<'a> ArgumentTokens<'a> {
+	/// The next word, along with its position in the arguments
+	pub fn next_token(&mut self) -> Option<ArgumentToken> {
+		let rest = &self.args[self.position..];
+		let start = self.position + (rest.len() - rest.trim_start().len());
+		self.position = start;
+		if start == self.args.len() {
+			return None;
+		}
+
+		let mut value = String::new();
+		let mut quote = None;
+		let mut escaped = false;
+		let mut end = self.args.len();
+
+		for (i, c) in self.args[start..].char_indices() {
+			if escaped {
+				value.push(c);
+				escaped = false;
+				continue;
+			}
+
+			match (quote, c) {
+				(Some('\''), '\'') => quote = None,
+				(Some('\''), _) => value.push(c),
+				(_, '\\') => escaped = true,
+				(Some(_), '"') => quote = None,
+				(None, '"') | (None, '\'') => quote = Some(c),
+				(None, _) if c.is_whitespace() => {
+					end = start + i;
+					break;
+				},
+				_ => value.push(c)
+			}
+		}
+
+		self.position = end;
+
+		Some(ArgumentToken {
+			start: start,
+			end: end,
+			value: value,
+			open_quote: quote
+		})
+	}
+}
+
+impl<'a> Iterator for ArgumentTokens<'a> {
+	type Item = String;
+
+	fn next(&mut self) -> Option<String> {
+		self.next_token().map(|t| t.value)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_tokenize_arguments() {
+		let args: Vec<String> = tokenize_arguments("  one two\tthree  ").collect();
+		assert_eq!(vec!["one", "two", "three"], args);
+
+		let args: Vec<String> = tokenize_arguments(r#"a"b c"d 'it''s' "\\" \"x"#).collect();
+		assert_eq!(vec!["ab cd", "its", "\\", "\"x"], args);
+
+		let args: Vec<String> = tokenize_arguments("\"\" ''").collect();
+		assert_eq!(vec!["", ""], args);
+
+		assert_eq!(0, tokenize_arguments("   ").count());
+	}
+
+	#[test]
+	fn test_partial_argument() {
+		assert_eq!(ArgumentToken { start: 4, end: 11, value: "home n".into(), open_quote: Some('"') },
+		           partial_argument("set \"home n"));
+		assert_eq!(ArgumentToken { start: 4, end: 11, value: "home n".into(), open_quote: None },
+		           partial_argument("set home\\ n"));
+		assert_eq!(ArgumentToken { start: 9, end: 9, value: "".into(), open_quote: None },
+		           partial_argument("set home "));
+		assert_eq!(ArgumentToken { start: 9, end: 11, value: " ".into(), open_quote: Some('\'') },
+		           partial_argument("set home ' "));
+	}
+
+	#[test]
+	fn test_quote_argument() {
+		assert_eq!("home", quote_argument("home", None));
+		assert_eq!("\"home lab\"", quote_argument("home lab", None));
+		assert_eq!("\"\"", quote_argument("", None));
+		assert_eq!("\"say \\\"hi\\\"\"", quote_argument("say \"hi\"", None));
+		assert_eq!("'it'\\''s'", quote_argument("it's", Some('\'')));
+
+		for value in &["home lab", "it's", "a\\b \"c\"", ""] {
+			for quote in &[None, Some('"'), Some('\'')] {
+				let args: Vec<String> = tokenize_arguments(&quote_argument(value, *quote)).collect();
+				assert_eq!(vec![value.to_string()], args);
+			}
+		}
+	}
+}
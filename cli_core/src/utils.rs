@@ -41,6 +41,56 @@ pub fn longest_common_prefix<'a>(strings: &'a [&'a str]) -> Option<&'a str> {
 	}
 }
 
+/// Number of terminal columns the character occupies. Combining marks and control
+/// characters take no space, East Asian wide characters and most emoji take two columns.
+pub fn char_display_width(c: char) -> usize {
+	let c = c as u32;
+
+	match c {
+		0x00..=0x1F | 0x7F..=0x9F => 0,
+		0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x200B..=0x200F |
+		0x20D0..=0x20FF | 0xFE00..=0xFE0F | 0xFE20..=0xFE2F => 0,
+		0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF |
+		0x4E00..=0x9FFF | 0xA000..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF |
+		0xFE30..=0xFE4F | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 | 0x1F300..=0x1F64F |
+		0x1F900..=0x1F9FF | 0x20000..=0x3FFFD => 2,
+		_ => 1
+	}
+}
+
+/// Number of terminal columns the characters occupy
+pub fn chars_display_width(chars: &[char]) -> usize {
+	chars.iter().map(|c| char_display_width(*c)).sum()
+}
+
+/// Number of terminal columns the string occupies
+pub fn str_display_width(s: &str) -> usize {
+	s.chars().map(char_display_width).sum()
+}
+
+/// Number of terminal columns the string occupies when printed, ignoring the ANSI escape
+/// sequences that set colors or move the cursor.
+pub fn printed_str_width(s: &str) -> usize {
+	let mut width = 0;
+	let mut chars = s.chars();
+
+	while let Some(c) = chars.next() {
+		if c != '\x1b' {
+			width += char_display_width(c);
+			continue;
+		}
+
+		if chars.next() == Some('[') {
+			// CSI, skip up to and including the final byte
+			for c in chars.by_ref() {
+				if ('\x40'..='\x7e').contains(&c) { break; }
+			}
+		}
+	}
+
+	width
+}
+
 /// Formats the strings in autocomplete-style column notation. Fills the width of
 /// the entire line with a string plus the desired spacing characters. Preserves 
 /// the ordering in columns.
@@ -71,7 +121,8 @@ pub fn format_in_columns<W: FmtWrite>(strings: &[&str], width: u16, spacing: u16
 		if plus_one_width <= width {
 			c + 1
 		} else {
-			c
+			// at least one column, even if the strings are wider than the terminal
+			max(c, 1)
 		}
 	};
 
@@ -147,6 +198,15 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn test_display_width() {
+		assert_eq!(5, str_display_width("hello"));
+		assert_eq!(3, str_display_width("e\u{301}t\u{e9}"));
+		assert_eq!(4, str_display_width("日本"));
+		assert_eq!(2, chars_display_width(&['\u{1F600}']));
+		assert_eq!(6, printed_str_width("\x1b[1;32m/p1/# \x1b[0m"));
+	}
+
 	#[test]
 	fn test_column_format() {
 		let s = vec!["A1", "A2", "A3", "B1", "B2", "C1", "C2"];
@@ -3,60 +3,101 @@ extern crate terminal_cli_termion;
 use terminal_cli::*;
 use terminal_cli_termion::*;
 
+use std::env;
+use std::process;
+
+fn commands(m: &mut CliExecutor, counter: &mut u32, switch: &mut bool) {
+	if let Some(mut m) = m.with_prefix("p3/") {
+		if let Some(mut ctx) = m.command("hello") {
+			ctx.get_terminal().print_line("Hello world - P3");
+		}
+
+		if let Some(mut ctx) = m.command("ping") {
+			ctx.get_terminal().print_line("Pong");
+		}
+
+		if let Some(mut m) = m.with_prefix("more/") {
+			if let Some(mut ctx) = m.command("here") {
+				ctx.get_terminal().print_line("Yep, here");
+			}
+		}
+	}
+
+	if let Some(mut ctx) = m.property_with_help("counter", validate_property_min_max(1, 100), "A counter between 1 and 100") {
+		ctx.apply(counter);
+	}
+	if let Some(mut ctx) = m.property_with_completion("switch", ValueBool, "", &CompletionProvider::list(vec!["true", "false"])) {
+		ctx.apply(switch);
+	}
+	if let Some(mut ctx) = m.command_with_help("p1/hello", "Print a greeting.") {
+		ctx.get_terminal().print_line("Hello world - P1");
+	}
+	if let Some(mut ctx) = m.command("p1/s1/hello") {
+		ctx.get_terminal().print_line("Hello world - P1 S1");
+	}
+	if let Some(mut ctx) = m.command_with_help("p1/s1/reset", "Reset the system.\nAsks for a confirmation first.") {
+		ctx.confirm("Are you sure?", false, |yes, ctx| {
+			ctx.get_terminal().print_line(if yes { "Reset." } else { "Cancelled." });
+		});
+	}
+	let greet = ArgumentSchema::new()
+		.arg(Argument::positional("name").help("Who to greet").complete(CompletionProvider::list(vec!["world", "Rust team"])))
+		.arg(Argument::option("times").value(validate_property_min_max(1u32, 10)).help("How many times"))
+		.arg(Argument::flag("loud").help("Shout the greeting"));
+	if let Some(mut ctx) = m.command_with_args("greet", "Greet someone.", &greet) {
+		let args = ctx.get_arguments();
+		let mut greeting = format!("Hello, {}!", args.get::<String>("name").unwrap_or_default());
+		if args.has("loud") {
+			greeting = greeting.to_uppercase();
+		}
+		for _ in 0..args.get::<u32>("times").unwrap_or(1) {
+			ctx.get_terminal().print_line(&greeting);
+		}
+	}
+	if let Some(mut ctx) = m.command("p2/hello") {
+		ctx.get_terminal().print_line("Hello world - P2");
+	}
+
+	if let Some(mut ctx) = m.command_with_help("login", "Log in with a password.") {
+		ctx.request_secret_input("Password: ", |password, ctx| {
+			ctx.get_terminal().print_line(&format!("Logged in, password has {} characters.", password.chars().count()));
+		});
+	}
+
+	if let Some(mut ctx) = m.command("lines") {
+		ctx.get_terminal().print_line("Line 1");
+		ctx.get_terminal().print_line("Line 2");
+		println!("Line 3");
+		println!("Line 4");
+	}
+}
+
 fn main() {
-	let options = PromptBufferOptions { echo: true, ..Default::default() };
+	let mut counter = 1;
+	let mut switch = false;
+
+	// `prompt p1/hello` executes a single command, without the prompt
+	let args: Vec<String> = env::args().skip(1).collect();
+	if !args.is_empty() {
+		let line = args_to_line(&args);
+		let result = execute_line(&line, &i18n::English, &mut StdoutTerminal, |m| commands(m, &mut counter, &mut switch));
+		process::exit(result.exit_code());
+	}
+
+	let options = PromptBufferOptions { echo: true, help_enabled: true, ..Default::default() };
 	
 	let mut term = TerminalTermion::new();	
 	let mut prompt = PromptBuffer::new(options);
 	prompt.print_prompt(&mut term);
-
-	let mut counter = 1;
-	let mut switch = false;
 	
 	loop {
+		// follow the resizes of the terminal window
+		if let Some((columns, rows)) = term.get_size() {
+			prompt.set_terminal_size(columns, rows);
+		}
 
 		let key_result = prompt.handle_terminal_key(&mut term, |m| {
-			if let Some(mut m) = m.with_prefix("p3/") {
-				if let Some(mut ctx) = m.command("hello") {
-					ctx.get_terminal().print_line("Hello world - P3");
-				}
-
-				if let Some(mut ctx) = m.command("ping") {
-					ctx.get_terminal().print_line("Pong");
-				}
-
-				if let Some(mut m) = m.with_prefix("more/") {
-					if let Some(mut ctx) = m.command("here") {
-						ctx.get_terminal().print_line("Yep, here");
-					}
-				}
-			}
-
-			if let Some(mut ctx) = m.property("counter", validate_property_min_max(1, 100)) {
-				ctx.apply(&mut counter);
-			}
-			if let Some(mut ctx) = m.property("switch", ValueBool) {
-				ctx.apply(&mut switch);
-			}
-			if let Some(mut ctx) = m.command("p1/hello") {
-				ctx.get_terminal().print_line("Hello world - P1");
-			}
-			if let Some(mut ctx) = m.command("p1/s1/hello") {
-				ctx.get_terminal().print_line("Hello world - P1 S1");
-			}
-			if let Some(mut ctx) = m.command("p1/s1/reset") {
-				ctx.get_terminal().print_line("Reset.");
-			}
-			if let Some(mut ctx) = m.command("p2/hello") {
-				ctx.get_terminal().print_line("Hello world - P2");
-			}
-
-			if let Some(mut ctx) = m.command("lines") {
-				ctx.get_terminal().print_line("Line 1");
-				ctx.get_terminal().print_line("Line 2");
-				println!("Line 3");
-				println!("Line 4");
-			}
+			commands(m, &mut counter, &mut switch);
 		});
 
 		match key_result {
@@ -0,0 +1,99 @@
+//! Client for the `UnixConsoleServer` of the terminal_cli crate. Puts the local terminal
+//! in raw mode, relays the typed keys to the console socket and prints its output.
+//!
+//! Usage: `terminal_cli_client <socket path>`
+
+extern crate terminal_cli;
+extern crate terminal_cli_termion;
+
+#[cfg(unix)]
+fn main() {
+	use terminal_cli::*;
+	use terminal_cli_termion::*;
+
+	use std::env;
+	use std::io::{stdout, Read, Write};
+	use std::os::unix::net::UnixStream;
+	use std::process;
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicBool, Ordering};
+	use std::thread;
+
+	let path = match env::args().nth(1) {
+		Some(path) => path,
+		None => {
+			eprintln!("Usage: terminal_cli_client <socket path>");
+			process::exit(2);
+		}
+	};
+
+	let mut socket = match UnixStream::connect(&path) {
+		Ok(socket) => socket,
+		Err(e) => {
+			eprintln!("Can't connect to {}: {}", path, e);
+			process::exit(1);
+		}
+	};
+
+	let closed = Arc::new(AtomicBool::new(false));
+
+	{
+		let mut socket = socket.try_clone().unwrap();
+		let closed = closed.clone();
+
+		thread::spawn(move || {
+			let mut buf = [0; 1024];
+			loop {
+				match socket.read(&mut buf) {
+					Ok(0) | Err(_) => break,
+					Ok(n) => {
+						let stdout = stdout();
+						let mut stdout = stdout.lock();
+						let _ = stdout.write_all(&buf[..n]);
+						let _ = stdout.flush();
+					}
+				}
+			}
+			closed.store(true, Ordering::SeqCst);
+		});
+	}
+
+	let mut term = TerminalTermion::new();
+	let mut size = None;
+
+	while !closed.load(Ordering::SeqCst) {
+		// the server learns the window size from a window size report
+		let new_size = term.get_size();
+		if new_size != size {
+			size = new_size;
+			if let Some((columns, rows)) = size {
+				let mut report = vec![];
+				encode_key(Key::WindowSize { rows: rows, columns: columns }, &mut report).unwrap();
+				if socket.write_all(&report).is_err() {
+					break;
+				}
+			}
+		}
+
+		// the server decodes the keys, same as a local terminal would
+		let mut buf = [0; 64];
+		match term.read_raw(&mut buf, 100) {
+			Ok(0) => (),
+			Ok(n) => {
+				if socket.write_all(&buf[..n]).is_err() {
+					break;
+				}
+			},
+			Err(_) => break
+		}
+	}
+
+	drop(term);
+	println!();
+}
+
+#[cfg(not(unix))]
+fn main() {
+	eprintln!("Unix domain sockets aren't supported on this platform.");
+	std::process::exit(1);
+}
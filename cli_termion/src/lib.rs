@@ -15,6 +15,7 @@ use self::termion::event::Key;
 use self::termion::input::TermRead;
 use self::termion::raw::IntoRawMode;
 
+#[cfg(not(unix))]
 use std::io::prelude::*;
 use std::io::{stdout, stdin, Bytes, Write, Stdout};
 use std::fmt::Write as FmtWrite;
@@ -86,6 +87,19 @@ impl TerminalTermion {
 	}
 }
 
+impl TerminalTermion {
+	/// Size of the terminal, in columns and rows
+	pub fn get_size(&self) -> Option<(u16, u16)> {
+		termion::terminal_size().ok()
+	}
+
+	/// Read the raw terminal input, for relaying it to a remote console. Waits for at most
+	/// `timeout_ms` milliseconds and returns 0 if nothing was typed.
+	pub fn read_raw(&mut self, buf: &mut [u8], timeout_ms: i32) -> Result<usize, TerminalError> {
+		read_bytes(buf, Some(timeout_ms))
+	}
+}
+
 impl Drop for TerminalTermion {
 	fn drop(&mut self) {
 		write!(self.stdout, "{}", self::termion::cursor::Show).unwrap();
@@ -106,14 +120,55 @@ impl FmtWrite for TerminalTermion {
     }
 }
 
-impl CharacterTerminalReader for TerminalTermion {
-	fn read(&mut self) -> Result<::terminal_cli::Key, TerminalError> {
-		let mut input = stdin().bytes();
+/// How long to wait for the rest of an escape sequence before a lone ESC byte is
+/// reported as the Escape key, in milliseconds.
+const ESCAPE_TIMEOUT_MS: i32 = 50;
 
+/// Read from the standard input, bypassing the buffering of `Stdin` so that the timeout
+/// sees all the pending input. Returns 0 if the timeout elapsed.
+#[cfg(unix)]
+fn read_bytes(buf: &mut [u8], timeout_ms: Option<i32>) -> Result<usize, TerminalError> {
+	if let Some(timeout_ms) = timeout_ms {
+		let mut fd = libc::pollfd { fd: 0, events: libc::POLLIN, revents: 0 };
+		match unsafe { libc::poll(&mut fd, 1, timeout_ms) } {
+			0 => { return Ok(0); },
+			r if r < 0 => { return Err(TerminalError::Error); },
+			_ => ()
+		}
+	}
+
+	match unsafe { libc::read(0, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) } {
+		0 => Err(TerminalError::EndOfStream),
+		r if r < 0 => Err(TerminalError::Error),
+		r => Ok(r as usize)
+	}
+}
 
+#[cfg(not(unix))]
+fn read_bytes(buf: &mut [u8], _timeout_ms: Option<i32>) -> Result<usize, TerminalError> {
+	match stdin().read(buf) {
+		Ok(0) => Err(TerminalError::EndOfStream),
+		Ok(n) => Ok(n),
+		Err(_) => Err(TerminalError::Error)
+	}
+}
+
+/// Read a single byte from the standard input. Returns `None` if the timeout elapsed.
+fn read_byte(timeout_ms: Option<i32>) -> Result<Option<u8>, TerminalError> {
+	let mut byte = [0u8];
+	match read_bytes(&mut byte, timeout_ms)? {
+		0 => Ok(None),
+		_ => Ok(Some(byte[0]))
+	}
+}
+
+impl CharacterTerminalReader for TerminalTermion {
+	fn read(&mut self) -> Result<::terminal_cli::Key, TerminalError> {
 		loop {
-			match input.next() {
-				Some(Ok(b)) => {
+			let timeout = if self.decoder.is_pending() { Some(ESCAPE_TIMEOUT_MS) } else { None };
+
+			match read_byte(timeout)? {
+				Some(b) => {
 					let d = self.decoder.decode(b);
 					match d {
 						Ok(k) => {
@@ -127,15 +182,13 @@ impl CharacterTerminalReader for TerminalTermion {
 						}
 					}
 				},
-				Some(Err(_)) => {
-					return Err(TerminalError::Error);
-				},
 				None => {
-					return Err(TerminalError::EndOfStream);
+					// the input went idle in the middle of a sequence
+					if let Some(k) = self.decoder.flush() {
+						return Ok(k);
+					}
 				}
 			}
 		}
-
-
 	}
 }